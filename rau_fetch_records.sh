@@ -1,3 +1,3 @@
 #!/bin/bash
 config_name="$1"
-rau "$config_name" -r | awk -F', ' '{print $1 "," $2}' | sed 's/ID: //; s/Name: //'
+rau "$config_name" -r --format text | awk -F', ' '{print $1 "," $2}' | sed 's/ID: //; s/Name: //'
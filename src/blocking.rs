@@ -0,0 +1,127 @@
+// Blocking code path, built with `--features blocking`. A plain single-record query or
+// `--schema` is one request, so it is served with reqwest's blocking client without starting
+// the tokio runtime. Anything else returns None from try_run and runs on the async path.
+
+use crate::{api_url, find_table, parse_record, print_queried_record, with_defaults, Options, RauError, RetryPolicy, Settings, TableConfig, TablesResponse};
+use clap::parser::ValueSource;
+use reqwest::blocking::Client;
+use reqwest::header::RETRY_AFTER;
+use reqwest::StatusCode;
+use std::error::Error;
+use std::fs;
+use std::time::Duration;
+
+// Flags handled by the async client or run loop; giving any of them uses the async path
+const ASYNC_FLAGS: &[&str] = &[
+    "glob",
+    "explain",
+    "verbose",
+    "curl",
+    "header",
+    "limit_requests",
+    "base_delay",
+    "max_body_size",
+    "assume_schema",
+    "startup_retries",
+];
+
+// Run the command if the blocking path can serve it, or return None to use the async path
+pub fn try_run(matches: &clap::ArgMatches) -> Option<Result<(), Box<dyn Error>>> {
+    let config_name = matches.get_one::<String>("config")?;
+    let config = Settings::new(!matches.get_flag("no_env"), matches.get_one::<String>("config_format").map(String::as_str)).ok()?;
+    let table_config = config.tables.get(config_name)?;
+    if !config.extra_headers.is_empty() {
+        return None;
+    }
+
+    let table_matches = match with_defaults(matches, &config.defaults, &table_config.defaults) {
+        Ok(table_matches) => table_matches,
+        Err(err) => return Some(Err(err)),
+    };
+    let matches = table_matches.as_ref().unwrap_or(matches);
+    if ASYNC_FLAGS.iter().any(|id| matches.value_source(id) == Some(ValueSource::CommandLine)) {
+        return None;
+    }
+    let opts = match Options::from_matches(matches) {
+        Ok(opts) => opts,
+        Err(err) => return Some(Err(err)),
+    };
+    if !is_simple(&opts) {
+        return None;
+    }
+    Some(run(&config, table_config, &opts))
+}
+
+// `--schema` as JSON, or a read of one record printed as it comes back
+fn is_simple(opts: &Options) -> bool {
+    if opts.command.is_some() {
+        return false;
+    }
+    if opts.output_schema {
+        return !opts.markdown;
+    }
+    opts.record_id.is_some()
+        && !opts.is_write()
+        && !opts.output_fields
+        && !opts.output_recent
+        && opts.options_field.is_none()
+        && opts.compare_to.is_none()
+        && !opts.no_computed
+        && !opts.record_url
+}
+
+fn run(config: &Settings, table_config: &TableConfig, opts: &Options) -> Result<(), Box<dyn Error>> {
+    let client = Client::new();
+    let api_key = config.api_key()?;
+
+    if opts.output_schema {
+        let url = format!("{}/v0/meta/bases/{}/tables", api_url(), table_config.base_id);
+        let text = get(&client, &api_key, &url, &opts.retry, "Failed to fetch schema")?;
+        let resp: TablesResponse = serde_json::from_str(&text)?;
+        let table = find_table(resp.tables, &table_config.table_name);
+        // Keep the cache as fresh as the async path would
+        fs::write(&config.paths.cache_file, serde_json::to_string(&table)?)?;
+        println!("{}", serde_json::to_string_pretty(&table.fields)?);
+        return Ok(());
+    }
+
+    let record_id = opts.record_id.ok_or("A record ID is required")?;
+    let url = format!("{}/v0/{}/{}/{}", api_url(), table_config.base_id, table_config.table_name, record_id);
+    let text = get(&client, &api_key, &url, &opts.retry, "Failed to query record")?;
+    let record = parse_record(&text)?;
+    print_queried_record(&record, &opts.fields, None, opts)?;
+    Ok(())
+}
+
+// GET a URL and return the body, retrying 429s, server errors and connection failures with
+// the same backoff as the async client
+pub(crate) fn get(client: &Client, api_key: &str, url: &str, retry: &RetryPolicy, failure: &str) -> Result<String, Box<dyn Error>> {
+    let mut attempt = 0;
+    loop {
+        let result = client.get(url).bearer_auth(api_key).send();
+        let retryable = match &result {
+            Ok(resp) => resp.status() == StatusCode::TOO_MANY_REQUESTS || resp.status().is_server_error(),
+            Err(err) => err.is_connect() || err.is_timeout(),
+        };
+        if retryable && attempt < retry.max_retries {
+            let retry_after = result
+                .as_ref()
+                .ok()
+                .and_then(|resp| resp.headers().get(RETRY_AFTER))
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse::<u64>().ok())
+                .map(Duration::from_secs);
+            std::thread::sleep(retry.delay(attempt, retry_after));
+            attempt += 1;
+            continue;
+        }
+
+        let resp = result?;
+        let status = resp.status();
+        let text = resp.text()?;
+        if !status.is_success() {
+            return Err(RauError::from_status(status, format!("{}. Status: {}, Response: {}", failure, status, text)));
+        }
+        return Ok(text);
+    }
+}
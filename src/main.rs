@@ -1,54 +1,133 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
 use clap::{Arg, ArgAction, Command};
-use config::{Config, ConfigError, Environment, File};
-use reqwest::Client;
+use config::{Config, ConfigError, Environment, File, FileFormat};
+use futures::{stream, StreamExt, TryStreamExt};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, RETRY_AFTER};
+use reqwest::{Client, IntoUrl, Method, RequestBuilder, Response, StatusCode};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use regex::Regex;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Map, Value};
 use std::collections::HashMap;
 use std::fs;
 use std::io::{self, Write};
 use std::env;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[cfg(feature = "blocking")]
+mod blocking;
+mod output;
+
+use output::OutputFormat;
+
+// Host every Airtable API request is sent to, unless RAU_API_URL points elsewhere (e.g. a mock server)
+const API_URL: &str = "https://api.airtable.com";
+
+fn api_url() -> String {
+    env::var("RAU_API_URL").map(|url| url.trim_end_matches('/').to_string()).unwrap_or_else(|_| API_URL.to_string())
+}
+
+// Set by the first Ctrl-C; loops stop scheduling new requests once it is set
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Default, Serialize, Deserialize)]
 struct Record {
     id: String,
     fields: serde_json::Value,
+    #[serde(rename = "createdTime", default, skip_serializing_if = "String::is_empty")]
+    created_time: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 struct RecordsResponse {
     records: Vec<Record>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    offset: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 struct Settings {
-    api_key: String,
+    api_key: Option<String>,
+    api_key_source: Option<String>,
+    keyring_service: Option<String>,
+    keyring_account: Option<String>,
     tables: HashMap<String, TableConfig>,
     paths: PathsConfig,
+    // Flag values applied to every table unless given on the command line
+    #[serde(default)]
+    defaults: HashMap<String, Value>,
+    // Named filterByFormula formulas, used as `--filter @name`
+    #[serde(default)]
+    filters: HashMap<String, String>,
+    // Headers added to every request, e.g. for an API gateway
+    #[serde(default)]
+    extra_headers: HashMap<String, String>,
+    // JSON-lines file recording every create and update
+    audit_log: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 struct PathsConfig {
+    // Accepted for existing configs; the config itself is located through RAU_CONFIG_PATH
+    #[allow(dead_code)]
     config_file: String,
     cache_file: String,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 struct TableConfig {
+    // The configuration's key in `tables`, filled in after loading
+    #[serde(skip)]
+    name: String,
     base_id: String,
     table_name: String,
+    // Field that identifies a record, used when an operation needs a key and none is given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    key_field: Option<String>,
+    // Flag values for this table, taking precedence over the global `defaults`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    defaults: HashMap<String, Value>,
+    // Listing order and formula used when --sort and --filter are not given
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    default_sort: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    default_filter: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 struct Field {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    id: String,
     name: String,
     #[serde(rename = "type")]
     field_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    options: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 struct Table {
+    id: String,
     name: String,
+    #[serde(rename = "primaryFieldId", default, skip_serializing_if = "String::is_empty")]
+    primary_field_id: String,
     fields: Vec<Field>,
+    #[serde(default)]
+    views: Vec<View>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct View {
+    id: String,
+    name: String,
+    #[serde(rename = "type")]
+    view_type: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -57,321 +136,4607 @@ struct TablesResponse {
 }
 
 impl Settings {
-    fn new() -> Result<Self, ConfigError> {
-        let main_config_path = env::var("RAU_CONFIG_PATH")
-            .unwrap_or_else(|_| "/path/to/default/main_config.toml".to_string());
+    // Config file location, from RAU_CONFIG_PATH
+    fn path() -> String {
+        env::var("RAU_CONFIG_PATH").unwrap_or_else(|_| "/path/to/default/main_config.toml".to_string())
+    }
 
-        let settings = Config::builder()
-            .add_source(File::with_name(&main_config_path))
-            .add_source(Environment::with_prefix("AIRTABLE"))
-            .build()?;
-        settings.try_deserialize()
+    // Config file format from --config-format or RAU_CONFIG_FORMAT, or None to go by the file extension
+    fn format(flag: Option<&str>) -> Result<Option<FileFormat>, ConfigError> {
+        let name = match flag {
+            Some(name) => name.to_string(),
+            None => match env::var("RAU_CONFIG_FORMAT") {
+                Ok(name) if !name.is_empty() => name,
+                _ => return Ok(None),
+            },
+        };
+        match name.to_lowercase().as_str() {
+            "toml" => Ok(Some(FileFormat::Toml)),
+            "yaml" | "yml" => Ok(Some(FileFormat::Yaml)),
+            "json" => Ok(Some(FileFormat::Json)),
+            other => Err(ConfigError::Message(format!("Unknown config format '{}', expected toml, yaml or json", other))),
+        }
+    }
+
+    fn new(use_env: bool, format: Option<&str>) -> Result<Self, ConfigError> {
+        let main_config_path = Self::path();
+
+        // An explicit format skips extension-based detection, for files named without one
+        let source = match Self::format(format)? {
+            Some(format) => File::new(&main_config_path, format),
+            None => File::with_name(&main_config_path),
+        };
+        let mut builder = Config::builder().add_source(source);
+        if use_env {
+            builder = builder.add_source(Environment::with_prefix("AIRTABLE"));
+        }
+        let mut settings: Settings = builder.build()?.try_deserialize()?;
+        for (name, table_config) in settings.tables.iter_mut() {
+            table_config.name = name.clone();
+        }
+        Ok(settings)
     }
-}
 
-async fn fetch_available_fields(api_key: &str, base_id: &str, table_name: &str) -> Result<Vec<Field>, Box<dyn std::error::Error>> {
-    let client = Client::new();
-    let url = format!("https://api.airtable.com/v0/meta/bases/{}/tables", base_id);
-    let resp: TablesResponse = client
-        .get(&url)
-        .bearer_auth(api_key)
-        .send()
-        .await?
-        .json()
-        .await?;
+    // Headers from `extra_headers` and `--header 'Name: Value'` flags, the flags taking precedence.
+    // Authorization is reserved for the API key.
+    fn request_headers(&self, flags: &[&str]) -> Result<HeaderMap, Box<dyn std::error::Error>> {
+        let mut pairs: Vec<(&str, &str)> = self.extra_headers.iter().map(|(name, value)| (name.as_str(), value.as_str())).collect();
+        for flag in flags {
+            let (name, value) = flag.split_once(':').ok_or_else(|| format!("Invalid header '{}', expected 'Name: Value'", flag))?;
+            pairs.push((name.trim(), value.trim()));
+        }
 
-    for table in resp.tables {
-        if table_name == table.name {
-            return Ok(table.fields);
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            let name = HeaderName::from_bytes(name.as_bytes()).map_err(|_| format!("Invalid header name '{}'", name))?;
+            if name == AUTHORIZATION {
+                return Err("The Authorization header is set from the API key and cannot be overridden".into());
+            }
+            let value = HeaderValue::from_str(value).map_err(|_| format!("Invalid value for header '{}'", name))?;
+            headers.insert(name, value);
         }
+        Ok(headers)
     }
 
-    Ok(Vec::new())
+    // Keyring entry holding the API key when `api_key_source = "keyring"`
+    fn keyring_entry(&self) -> keyring::Result<keyring::Entry> {
+        let service = self.keyring_service.as_deref().unwrap_or("rau");
+        let account = self.keyring_account.as_deref().unwrap_or("api_key");
+        keyring::Entry::new(service, account)
+    }
+
+    fn api_key(&self) -> Result<String, Box<dyn std::error::Error>> {
+        match self.api_key_source.as_deref() {
+            Some("keyring") => Ok(self.keyring_entry()?.get_password()?),
+            Some("config") | None => self
+                .api_key
+                .clone()
+                .ok_or_else(|| RauError::Usage("No api_key in config or AIRTABLE_API_KEY environment variable".to_string()).into()),
+            Some(other) => Err(RauError::Usage(format!("Unknown api_key_source '{}', expected 'config' or 'keyring'", other)).into()),
+        }
+    }
 }
 
-async fn cache_available_fields(api_key: &str, base_id: &str, table_name: &str, cache_file: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let fields = fetch_available_fields(api_key, base_id, table_name).await?;
-    let fields_json = serde_json::to_string(&fields)?;
-    let mut file = fs::File::create(cache_file)?;
-    file.write_all(fields_json.as_bytes())?;
+// Prompt for an API key on stdin and store it in the system keychain
+fn set_keyring_key(config: &Settings) -> Result<(), Box<dyn std::error::Error>> {
+    eprint!("Airtable API key: ");
+    io::stderr().flush()?;
+    let mut key = String::new();
+    io::stdin().read_line(&mut key)?;
+    let key = key.trim();
+    if key.is_empty() {
+        return Err("No API key entered".into());
+    }
+
+    config.keyring_entry()?.set_password(key)?;
+    eprintln!("Stored API key in the system keychain");
+    if config.api_key_source.as_deref() != Some("keyring") {
+        eprintln!("Set api_key_source = \"keyring\" in the config to use it");
+    }
     Ok(())
 }
 
-fn read_cached_fields(cache_file: &str) -> io::Result<Vec<Field>> {
-    let fields_json = fs::read_to_string(cache_file)?;
-    let fields: Vec<Field> = serde_json::from_str(&fields_json)?;
-    Ok(fields)
+// Print a PASS or FAIL line for one `doctor` check, with a hint on how to fix a failure
+fn doctor_check(passed: bool, message: &str, hint: &str) -> bool {
+    if passed {
+        println!("PASS  {}", message);
+    } else {
+        println!("FAIL  {}", message);
+        println!("      {}", hint);
+    }
+    passed
 }
 
-// Helper function to parse JSON strings into JSON objects
-fn parse_json_string(value: &str) -> serde_json::Value {
-    serde_json::from_str(value).unwrap_or_else(|_| json!(value))
-}
+// Hint for a doctor check whose request never got a response
+const NETWORK_HINT: &str = "Check the network connection, proxy settings and RAU_API_URL";
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Load configuration
-    let config = Settings::new()?;
-    let api_key = &config.api_key;
+// Check the config, API key, authentication and access to every configured table, failing
+// with a usage error if the config or key is missing, an auth error if the token is rejected,
+// and a plain error if any other check fails
+async fn doctor(matches: &clap::ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let mut failures = 0;
+    let config_path = Settings::path();
+    let config = match Settings::new(!matches.get_flag("no_env"), matches.get_one::<String>("config_format").map(String::as_str)) {
+        Ok(config) => {
+            doctor_check(true, &format!("Config loads from {} ({} tables)", config_path, config.tables.len()), "");
+            config
+        }
+        Err(err) => {
+            doctor_check(false, &format!("Config loads from {}: {}", config_path, err), "Set RAU_CONFIG_PATH to your config.toml and check it against the README");
+            return Err(RauError::Usage(format!("The config does not load: {}", err)).into());
+        }
+    };
 
-    // Create CLI interface
-    let matches = Command::new("Airtable CLI")
-        .version("1.0")
-        .about("Update or query Airtable records from the CLI")
-        .arg(
-            Arg::new("config")
-                .help("The name of the configuration to use")
-                .required(true)
-                .index(1),
-        )
-        .arg(
-            Arg::new("record_id")
-                .help("The ID of the record to update or query")
-                .required(false)
-                .index(2),
-        )
-        .arg(
-            Arg::new("fields")
-                .help("Fields to update in key=value format or fields to query for their values")
-                .index(3)
-                .num_args(1..)
-                .require_equals(false)
-                .required(false),
-        )
-        .arg(
-            Arg::new("schema")
-                .short('s')
-                .long("schema")
-                .action(ArgAction::SetTrue)
-                .help("Output the schema")
-                .conflicts_with("fields_flag"),
-        )
-        .arg(
-            Arg::new("fields_flag")
-                .short('f')
-                .long("fields")
-                .action(ArgAction::SetTrue)
-                .help("Output the fields")
-                .conflicts_with("schema"),
-        )
-        .arg(
-            Arg::new("recent")
-                .short('r')
-                .long("recent")
-                .action(ArgAction::SetTrue)
-                .help("Output the 100 most recent record IDs and their names"),
-        )
-        .get_matches();
+    let api_key = match config.api_key() {
+        Ok(api_key) => api_key,
+        Err(err) => {
+            doctor_check(false, &format!("API key is available: {}", err), "Add api_key to the config, export AIRTABLE_API_KEY, or run `rau set-key`");
+            return Err(RauError::Usage(format!("No API key: {}", err)).into());
+        }
+    };
+    // Personal access tokens look like patXXXXXXXXXXXXXX.<64 hex characters>
+    let well_formed = api_key.starts_with("pat") && api_key.split_once('.').is_some_and(|(id, secret)| id.len() == 17 && secret.len() == 64);
+    if !doctor_check(well_formed, "API key looks like a personal access token", "Create a personal access token at https://airtable.com/create/tokens; legacy API keys no longer work") {
+        failures += 1;
+    }
 
-    let config_name = matches.get_one::<String>("config").expect("Configuration name is required");
-    let record_id = matches.get_one::<String>("record_id");
-    let fields: Vec<&str> = matches.get_many::<String>("fields").unwrap_or_default().map(|s| s.as_str()).collect();
-    let output_schema = matches.get_flag("schema");
-    let output_fields = matches.get_flag("fields_flag");
-    let output_recent = matches.get_flag("recent");
+    let mut client = AirtableClient::new(&api_key);
+    let header_flags: Vec<&str> = matches.get_many::<String>("header").unwrap_or_default().map(String::as_str).collect();
+    client.headers = config.request_headers(&header_flags)?;
 
-    // Get the table configuration from the config
-    let table_config = config.tables.get(config_name).expect("Configuration not found in config");
+    // A request that fails outright is reported like a failed check, so the remaining checks still run
+    let mut rejected = false;
+    let (passed, message, hint) = match client.send(client.get(format!("{}/v0/meta/whoami", client.api_url))).await {
+        Ok((status, text)) if status.is_success() => {
+            let whoami: Value = serde_json::from_str(&text).unwrap_or_default();
+            let user = whoami.get("email").or_else(|| whoami.get("id")).and_then(Value::as_str).unwrap_or("unknown user");
+            (true, format!("Authenticated as {}", user), "")
+        }
+        Ok((status, _)) => {
+            rejected = status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN;
+            (false, format!("Authentication failed ({})", status), "Check that the token is current and has not been revoked")
+        }
+        Err(err) => (false, format!("Authentication could not be checked: {}", err), NETWORK_HINT),
+    };
+    if !doctor_check(passed, &message, hint) {
+        failures += 1;
+    }
 
-    // Cache available fields to a local file
-    let cache_file = &config.paths.cache_file;
-    cache_available_fields(&api_key, &table_config.base_id, &table_config.table_name, cache_file).await?;
+    let mut names: Vec<&String> = config.tables.keys().collect();
+    names.sort();
+    for name in names {
+        let table_config = &config.tables[name];
+        let url = format!("{}/v0/meta/bases/{}/tables", client.api_url, table_config.base_id);
+        let (status, text) = match client.send(client.get(&url)).await {
+            Ok(response) => response,
+            Err(err) => {
+                doctor_check(false, &format!("{}: cannot reach base {}: {}", name, table_config.base_id, err), NETWORK_HINT);
+                failures += 1;
+                continue;
+            }
+        };
+        let (passed, message, hint) = if !status.is_success() {
+            (
+                false,
+                format!("{}: cannot read the schema of base {} ({})", name, table_config.base_id, status),
+                "Give the token the schema.bases:read scope and access to this base",
+            )
+        } else {
+            let tables = match serde_json::from_str::<TablesResponse>(&text) {
+                Ok(tables) => tables,
+                Err(err) => {
+                    doctor_check(false, &format!("{}: unexpected schema response for base {}: {}", name, table_config.base_id, err), "Check that RAU_API_URL and any proxy point at the Airtable API");
+                    failures += 1;
+                    continue;
+                }
+            };
+            match tables.tables.iter().find(|table| table_config.table_name == table.name || table_config.table_name == table.id) {
+                Some(table) => match &table_config.key_field {
+                    Some(key_field) if !table.fields.iter().any(|field| &field.name == key_field) => (
+                        false,
+                        format!("{}: key_field '{}' is not a field of {}", name, key_field, table.name),
+                        "Set key_field to an existing field name; it is used by --upsert-on, --on-conflict and --dedup-on",
+                    ),
+                    _ => (true, format!("{}: {} ({} fields)", name, table.name, table.fields.len()), ""),
+                },
+                None => (
+                    false,
+                    format!("{}: no table '{}' in base {}", name, table_config.table_name, table_config.base_id),
+                    "Check table_name; it may have been renamed (a table ID keeps working across renames)",
+                ),
+            }
+        };
+        if !doctor_check(passed, &message, hint) {
+            failures += 1;
+        }
+    }
 
-    // Read available fields from cache
-    let available_fields = read_cached_fields(cache_file)?;
+    if rejected {
+        return Err(RauError::Auth(format!("{} check(s) failed; the API key was rejected", failures)).into());
+    }
+    if failures > 0 {
+        return Err(format!("{} check(s) failed", failures).into());
+    }
+    Ok(())
+}
 
-    if output_schema {
-        // Output the schema
-        let schema_json = serde_json::to_string_pretty(&available_fields)?;
-        println!("{}", schema_json);
-        return Ok(());
+// An equivalent curl command for a request, reading the API key from $AIRTABLE_API_KEY and
+// configured headers (`extra_headers` and --header), which may hold credentials too, from
+// $RAU_HEADER_<NAME> instead of including them
+fn curl_command(request: &reqwest::Request, custom_headers: &HeaderMap) -> String {
+    let mut command = format!("curl -X {} {}", request.method(), shell_quote(request.url().as_str()));
+    for (name, value) in request.headers() {
+        if name == AUTHORIZATION {
+            command.push_str(" -H \"Authorization: Bearer $AIRTABLE_API_KEY\"");
+        } else if custom_headers.contains_key(name) {
+            command.push_str(&format!(" -H {}\"${}\"", shell_quote(&format!("{}: ", name)), header_variable(name.as_str())));
+        } else {
+            command.push_str(&format!(" -H {}", shell_quote(&format!("{}: {}", name, String::from_utf8_lossy(value.as_bytes())))));
+        }
+    }
+    if let Some(body) = request.body().and_then(|body| body.as_bytes()) {
+        command.push_str(&format!(" --data {}", shell_quote(&String::from_utf8_lossy(body))));
     }
+    command
+}
 
-    // Filter out computed fields
-    let updatable_fields: Vec<String> = available_fields
-        .iter()
-        .filter(|field| field.field_type != "computed" && field.field_type != "formula" && field.field_type != "rollup" && field.field_type != "lookup" && field.field_type != "lastModifiedTime" && field.field_type != "createdTime")
-        .map(|field| field.name.clone())
-        .collect();
+// Environment variable standing in for a configured header in --curl output, e.g.
+// RAU_HEADER_X_API_KEY for X-Api-Key
+fn header_variable(name: &str) -> String {
+    let name: String = name.chars().map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' }).collect();
+    format!("RAU_HEADER_{}", name)
+}
 
-    if output_fields {
-        // Output the updatable fields
-        let fields_json = serde_json::to_string_pretty(&updatable_fields)?;
-        println!("{}", fields_json);
-        return Ok(());
+// Single-quote text for a POSIX shell
+fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "'\\''"))
+}
+
+// Read a response body chunk by chunk, aborting once it grows past `limit` bytes
+async fn read_limited(mut resp: Response, limit: usize) -> Result<String, Box<dyn std::error::Error>> {
+    let url = resp.url().clone();
+    let too_large = || format!("Aborting: response from {} is larger than {} bytes (--max-body-size)", url, limit);
+    if resp.content_length().is_some_and(|length| length > limit as u64) {
+        return Err(too_large().into());
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = resp.chunk().await? {
+        if body.len() + chunk.len() > limit {
+            return Err(too_large().into());
+        }
+        body.extend_from_slice(&chunk);
     }
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
 
-    if output_recent {
-        // Output the 100 most recent record IDs and their names
-        let client = Client::new();
-        let update_record_url = format!("https://api.airtable.com/v0/{}/{}", table_config.base_id, table_config.table_name);
-        let query_url = format!("{}/?maxRecords=100", update_record_url);
+// Exponential backoff between retries: base * 2^attempt, capped at max, and with full jitter a
+// random delay up to that. A longer Retry-After from the server is always honored.
+struct RetryPolicy {
+    max_retries: u32,
+    base_ms: u64,
+    max_ms: u64,
+    jitter: bool,
+}
 
-        // Make the API request
-        let query_resp = client
-            .get(&query_url)
-            .bearer_auth(&api_key)
-            .send()
-            .await?;
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 3,
+            base_ms: 500,
+            max_ms: 30_000,
+            jitter: true,
+        }
+    }
+}
 
-        let status = query_resp.status();
-        let text = query_resp.text().await?;
+impl RetryPolicy {
+    fn from_matches(matches: &clap::ArgMatches) -> Result<Self, Box<dyn std::error::Error>> {
+        let policy = RetryPolicy {
+            max_retries: *matches.get_one::<u32>("max_retries").expect("max_retries has a default"),
+            base_ms: *matches.get_one::<u64>("retry_base_ms").expect("retry_base_ms has a default"),
+            max_ms: *matches.get_one::<u64>("retry_max_ms").expect("retry_max_ms has a default"),
+            jitter: matches.get_one::<String>("retry_jitter").map(String::as_str) == Some("full"),
+        };
+        if policy.base_ms > policy.max_ms {
+            return Err(format!("--retry-base-ms ({}) must not be larger than --retry-max-ms ({})", policy.base_ms, policy.max_ms).into());
+        }
+        Ok(policy)
+    }
 
-        if status.is_success() {
-            let records_response: RecordsResponse = serde_json::from_str(&text)?;
-            for record in records_response.records {
-                let name = record.fields.get("Name").and_then(|v| v.as_str()).unwrap_or("<no name>").to_string();
-                println!("ID: {}, Name: {}", record.id, name);
-            }
-        } else {
-            eprintln!("Failed to query recent records. Status: {}, Response: {}", status, text);
+    fn delay(&self, attempt: u32, retry_after: Option<Duration>) -> Duration {
+        let backoff = self.base_ms.saturating_mul(1u64 << attempt.min(32)).min(self.max_ms);
+        let backoff = if self.jitter { rand::thread_rng().gen_range(0..=backoff) } else { backoff };
+        Duration::from_millis(backoff).max(retry_after.unwrap_or_default())
+    }
+}
+
+// Failures scripts can tell apart by exit code. Anything else exits with 1, and connection
+// failures and timeouts from reqwest with 6.
+#[derive(Debug)]
+enum RauError {
+    // Bad arguments or configuration (2)
+    Usage(String),
+    // The API key was rejected or lacks access (3)
+    Auth(String),
+    // The base, table or record does not exist (4)
+    NotFound(String),
+    // Still rate-limited once retries ran out (5)
+    RateLimited(String),
+    // The record no longer holds the values --if-match expected (7)
+    Conflict(String),
+    // Stopped by Ctrl-C before the work was done (130)
+    Interrupted(String),
+}
+
+impl RauError {
+    // Classify a failed API response by its status
+    fn from_status(status: StatusCode, message: String) -> Box<dyn std::error::Error> {
+        match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => RauError::Auth(message).into(),
+            StatusCode::NOT_FOUND => RauError::NotFound(message).into(),
+            StatusCode::TOO_MANY_REQUESTS => RauError::RateLimited(message).into(),
+            _ => message.into(),
         }
+    }
 
-        return Ok(());
+    fn exit_code(&self) -> i32 {
+        match self {
+            RauError::Usage(_) => 2,
+            RauError::Auth(_) => 3,
+            RauError::NotFound(_) => 4,
+            RauError::RateLimited(_) => 5,
+            RauError::Conflict(_) => 7,
+            RauError::Interrupted(_) => 130,
+        }
     }
+}
 
-    let client = Client::new();
-    let update_record_url = format!("https://api.airtable.com/v0/{}/{}", table_config.base_id, table_config.table_name);
+impl std::fmt::Display for RauError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RauError::Usage(message)
+            | RauError::Auth(message)
+            | RauError::NotFound(message)
+            | RauError::RateLimited(message)
+            | RauError::Conflict(message)
+            | RauError::Interrupted(message) => f.write_str(message),
+        }
+    }
+}
 
-    if let Some(record_id) = record_id {
-        if fields.is_empty() {
-            // Query all fields for their values
-            let query_record_url = format!("{}/{}", update_record_url, record_id);
+impl std::error::Error for RauError {}
 
-            // Make the API request
-            let query_resp = client
-                .get(&query_record_url)
-                .bearer_auth(&api_key)
-                .send()
-                .await?;
+// Exit code for an error that ended the run, as documented in the README
+fn exit_code(err: &(dyn std::error::Error + 'static)) -> i32 {
+    if let Some(err) = err.downcast_ref::<RauError>() {
+        return err.exit_code();
+    }
+    if err.downcast_ref::<ConfigError>().is_some() || err.downcast_ref::<clap::Error>().is_some() {
+        return 2;
+    }
+    if is_connection_error(err) {
+        return 6;
+    }
+    1
+}
 
-            let status = query_resp.status();
-            let text = query_resp.text().await?;
+// Authenticates every request and keeps track of how the API is responding
+struct AirtableClient {
+    http: Client,
+    // Base URL of the API, from `api_url()`
+    api_url: String,
+    api_key: String,
+    headers: HeaderMap,
+    verbose: bool,
+    strict_response: bool,
+    // Print nothing on success, only failures (--quiet-success)
+    quiet_success: bool,
+    request_limit: Option<usize>,
+    // Fixed pause before every request, including retries and further pages (--base-delay)
+    base_delay: Option<Duration>,
+    retry: RetryPolicy,
+    // Largest response body read before aborting (--max-body-size)
+    max_body_size: Option<usize>,
+    // Print each request as a curl command before sending it (--curl)
+    curl: bool,
+    // Append-only JSON-lines record of successful writes (audit_log or --audit-log)
+    audit_log: Option<String>,
+    // Report pages, batches and import chunks as JSON lines on stderr (--progress-json)
+    progress_json: bool,
+    requests: AtomicUsize,
+    rate_limited: AtomicUsize,
+    retries: AtomicUsize,
+    records_affected: AtomicUsize,
+}
 
-            if status.is_success() {
-                let record: Record = serde_json::from_str(&text)?;
-                for (field, value) in record.fields.as_object().unwrap() {
-                    eprint!("{}: ", field);
-                    println!("{}", value);
-                }
-            } else {
-                eprintln!("Failed to query record. Status: {}, Response: {}", status, text);
-            }
-        } else {
-            // Check if fields are in key=value format or not
-            let mut is_update = false;
-            for field in &fields {
-                if field.contains('=') {
-                    is_update = true;
-                    break;
-                }
-            }
+impl AirtableClient {
+    fn new(api_key: &str) -> Self {
+        AirtableClient {
+            http: Client::new(),
+            api_url: api_url(),
+            api_key: api_key.to_string(),
+            headers: HeaderMap::new(),
+            verbose: false,
+            strict_response: false,
+            quiet_success: false,
+            request_limit: None,
+            base_delay: None,
+            retry: RetryPolicy::default(),
+            max_body_size: None,
+            curl: false,
+            audit_log: None,
+            progress_json: false,
+            requests: AtomicUsize::new(0),
+            rate_limited: AtomicUsize::new(0),
+            retries: AtomicUsize::new(0),
+            records_affected: AtomicUsize::new(0),
+        }
+    }
 
-            if is_update {
-                // Parse fields into a JSON object
-                let mut fields_json = Map::new();
-                for field in fields {
-                    let parts: Vec<&str> = field.splitn(2, '=').collect();
-                    if parts.len() == 2 {
-                        fields_json.insert(parts[0].to_string(), parse_json_string(parts[1]));
-                    } else {
-                        eprintln!("Invalid field format: {}", field);
-                        return Ok(());
-                    }
-                }
+    fn request<U: IntoUrl>(&self, method: Method, url: U) -> RequestBuilder {
+        self.http.request(method, url).headers(self.headers.clone()).bearer_auth(&self.api_key)
+    }
 
-                // Update existing record
-                let update_data = json!({
-                    "records": [
-                        {
-                            "id": record_id,
-                            "fields": Value::Object(fields_json)
-                        }
-                    ]
-                });
+    fn get<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.request(Method::GET, url)
+    }
 
-                // Make the API request
-                let update_resp = client
-                    .patch(&update_record_url)
-                    .bearer_auth(&api_key)
-                    .header("Content-Type", "application/json")
-                    .json(&update_data)
-                    .send()
-                    .await?;
+    fn post<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.request(Method::POST, url)
+    }
 
-                let status = update_resp.status();
-                let text = update_resp.text().await?;
+    fn patch<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.request(Method::PATCH, url)
+    }
 
-                if status.is_success() {
-                    let _updated_records: RecordsResponse = serde_json::from_str(&text)?;
-                    println!("Updated Record");
-                } else {
-                    eprintln!("Failed to update record. Status: {}, Response: {}", status, text);
-                }
-            } else {
-                // Query specific fields for their values
-                let query_record_url = format!("{}/{}", update_record_url, record_id);
+    // Count a request against --limit-requests and wait out --base-delay before it is sent
+    async fn pace(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let sent = self.requests.fetch_add(1, Ordering::SeqCst);
+        if let Some(limit) = self.request_limit {
+            if sent >= limit {
+                return Err(format!("Aborting: reached the limit of {} requests (--limit-requests)", limit).into());
+            }
+        }
+        if let Some(delay) = self.base_delay {
+            tokio::time::sleep(delay).await;
+        }
+        Ok(())
+    }
 
-                // Make the API request
-                let query_resp = client
-                    .get(&query_record_url)
-                    .bearer_auth(&api_key)
-                    .send()
-                    .await?;
+    // Send a request and return its status and body text. Rate-limited requests are retried, and so
+    // are server errors and connection failures of GET and HEAD requests, which are safe to repeat.
+    async fn send(&self, request: RequestBuilder) -> Result<(StatusCode, String), Box<dyn std::error::Error>> {
+        self.send_with(request, true).await
+    }
 
-                let status = query_resp.status();
-                let text = query_resp.text().await?;
+    // Send a request, leaving connection failures to the caller unless `retry_connection_errors`
+    // is set; used where the caller has its own retry loop for them
+    async fn send_with(&self, request: RequestBuilder, retry_connection_errors: bool) -> Result<(StatusCode, String), Box<dyn std::error::Error>> {
+        let mut request = request.build()?;
+        let idempotent = request.method() == Method::GET || request.method() == Method::HEAD;
+        let mut attempt = 0;
+        let resp = loop {
+            self.pace().await?;
+            if self.curl {
+                eprintln!("{}", curl_command(&request, &self.headers));
+            }
+            let retry = request.try_clone().filter(|_| attempt < self.retry.max_retries);
+            let result = self.http.execute(request).await;
 
-                if status.is_success() {
-                    let record: Record = serde_json::from_str(&text)?;
-                    for field in fields {
-                        if let Some(value) = record.fields.get(field) {
-                            eprint!("{}: ", field);
-                            println!("{}", value);
-                        } else {
-                            println!("{}: <no value>", field);
-                        }
+            let (retry_after, reason) = match &result {
+                Ok(resp) => {
+                    if resp.status() == StatusCode::TOO_MANY_REQUESTS {
+                        self.rate_limited.fetch_add(1, Ordering::SeqCst);
                     }
-                } else {
-                    eprintln!("Failed to query record. Status: {}, Response: {}", status, text);
+                    if self.verbose {
+                        self.report_rate_limit(resp);
+                    }
+                    let retryable = resp.status() == StatusCode::TOO_MANY_REQUESTS || (idempotent && resp.status().is_server_error());
+                    if !retryable {
+                        break result?;
+                    }
+                    let retry_after = resp
+                        .headers()
+                        .get(RETRY_AFTER)
+                        .and_then(|value| value.to_str().ok())
+                        .and_then(|value| value.parse::<u64>().ok())
+                        .map(Duration::from_secs);
+                    (retry_after, resp.status().to_string())
                 }
+                Err(err) if retry_connection_errors && idempotent && (err.is_connect() || err.is_timeout()) => (None, err.to_string()),
+                Err(_) => break result?,
+            };
+            match retry {
+                Some(next) => {
+                    let delay = self.retry.delay(attempt, retry_after);
+                    if self.verbose {
+                        eprintln!("Retrying after {} in {}ms (retry {} of {})", reason, delay.as_millis(), attempt + 1, self.retry.max_retries);
+                    }
+                    self.retries.fetch_add(1, Ordering::SeqCst);
+                    tokio::time::sleep(delay).await;
+                    request = next;
+                    attempt += 1;
+                }
+                None => break result?,
             }
+        };
+
+        let status = resp.status();
+        let text = match self.max_body_size {
+            Some(limit) => read_limited(resp, limit).await?,
+            None => resp.text().await?,
+        };
+        Ok((status, text))
+    }
+
+    // Parse a successful response body. Unless strict parsing is on, an empty body or `{}`
+    // parses as an empty value, since some operations legitimately return nothing useful.
+    fn parse<T: DeserializeOwned + Default>(&self, text: &str) -> serde_json::Result<T> {
+        let trimmed = text.trim();
+        if !self.strict_response && (trimmed.is_empty() || trimmed == "{}") {
+            return Ok(T::default());
         }
-    } else {
-        // Create new record with empty structure
-        let empty_fields: Map<String, Value> = updatable_fields.into_iter().map(|f| (f, json!(null))).collect();
-        let create_data = json!({
-            "records": [
-                {
-                    "fields": Value::Object(empty_fields)
-                }
-            ]
-        });
+        serde_json::from_str(text)
+    }
 
-        // Make the API request
-        let create_resp = client
-            .post(&update_record_url)
-            .bearer_auth(&api_key)
-            .header("Content-Type", "application/json")
-            .json(&create_data)
-            .send()
-            .await?;
+    // Parse a single record; see parse_record
+    fn parse_record(&self, text: &str) -> Result<Record, Box<dyn std::error::Error>> {
+        parse_record(text)
+    }
 
-        let status = create_resp.status();
-        let text = create_resp.text().await?;
+    // Emit a --progress-json event, one JSON object per line on stderr
+    fn progress(&self, event: Value) {
+        if self.progress_json {
+            eprintln!("{}", event);
+        }
+    }
 
-        if status.is_success() {
-            let created_records: RecordsResponse = serde_json::from_str(&text)?;
-            if let Some(new_record) = created_records.records.get(0) {
-                eprintln!("Created Record ID");
-                println!("{}", new_record.id);
-            } else {
-                eprintln!("Failed to parse the response after creating a new record.");
-            }
+    // Append a successful write to the audit log, if one is configured. Failing to write it only
+    // warns, so the log never gets in the way of the operation itself.
+    fn audit(&self, table_config: &TableConfig, operation: &str, record_id: &str, fields: &Value) {
+        let Some(path) = &self.audit_log else {
+            return;
+        };
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs()).unwrap_or_default();
+        let entry = json!({
+            "timestamp": timestamp,
+            "config": table_config.name,
+            "base_id": table_config.base_id,
+            "table": table_config.table_name,
+            "record_id": record_id,
+            "operation": operation,
+            "fields": fields,
+        });
+        let written = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| writeln!(file, "{}", entry));
+        if let Err(err) = written {
+            eprintln!("Warning: could not write to audit log {}: {}", path, err);
+        }
+    }
+
+    // One-line account of what an invocation did, printed only when the operation itself took
+    // more than one request on top of the per-table schema refreshes
+    fn print_summary(&self, started: Instant, schema_requests: usize) {
+        let requests = self.requests.load(Ordering::SeqCst);
+        if self.quiet_success || requests <= schema_requests + 1 {
+            return;
+        }
+        eprintln!(
+            "Summary: {} requests, {} rate-limited (429), {} retried, {} records affected, {:.1}s elapsed",
+            requests,
+            self.rate_limited.load(Ordering::SeqCst),
+            self.retries.load(Ordering::SeqCst),
+            self.records_affected.load(Ordering::SeqCst),
+            started.elapsed().as_secs_f64()
+        );
+    }
+
+    // Print any rate-limit headers, falling back to what the client has observed so far
+    fn report_rate_limit(&self, resp: &Response) {
+        eprintln!("{} {} -> {}", resp.url().path(), resp.url().query().unwrap_or_default(), resp.status());
+        let headers: Vec<String> = resp
+            .headers()
+            .iter()
+            .filter(|(name, _)| name.as_str().contains("ratelimit") || *name == RETRY_AFTER)
+            .map(|(name, value)| format!("{}={}", name, value.to_str().unwrap_or("<binary>")))
+            .collect();
+        if headers.is_empty() {
+            eprintln!(
+                "Rate limit: no quota headers returned; {} request(s) sent, {} rate-limited (429), no client-side throttling",
+                self.requests.load(Ordering::SeqCst),
+                self.rate_limited.load(Ordering::SeqCst)
+            );
         } else {
-            eprintln!("Failed to create record. Status: {}, Response: {}", status, text);
+            eprintln!("Rate limit: {}", headers.join(", "));
         }
     }
+}
+
+async fn fetch_available_fields(client: &AirtableClient, base_id: &str, table_name: &str, retry_connection_errors: bool) -> Result<Table, Box<dyn std::error::Error>> {
+    let url = format!("{}/v0/meta/bases/{}/tables", client.api_url, base_id);
+    let (status, text) = client.send_with(client.get(&url), retry_connection_errors).await?;
+    if !status.is_success() {
+        return Err(RauError::from_status(status, format!("Failed to fetch schema. Status: {}, Response: {}", status, text)));
+    }
+    let resp: TablesResponse = serde_json::from_str(&text)?;
+    Ok(find_table(resp.tables, table_name))
+}
+
+// The table a config names, or an empty one without an ID when the base has no such table.
+// The records API accepts a table ID in place of its name, so configs may hold either.
+fn find_table(tables: Vec<Table>, table_name: &str) -> Table {
+    tables.into_iter().find(|table| table_name == table.name || table_name == table.id).unwrap_or_else(|| Table {
+        id: String::new(),
+        name: table_name.to_string(),
+        fields: Vec::new(),
+        views: Vec::new(),
+        primary_field_id: String::new(),
+    })
+}
+
+// Parse a single record. A record read always has a body, so an empty one is an error
+// whether or not strict parsing is on.
+fn parse_record(text: &str) -> Result<Record, Box<dyn std::error::Error>> {
+    let trimmed = text.trim();
+    if trimmed.is_empty() || trimmed == "{}" {
+        return Err("Airtable returned an empty response for the record".into());
+    }
+    Ok(serde_json::from_str(text)?)
+}
 
+async fn cache_available_fields(client: &AirtableClient, base_id: &str, table_name: &str, cache_file: &str, retry_connection_errors: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let table = fetch_available_fields(client, base_id, table_name, retry_connection_errors).await?;
+    let fields_json = serde_json::to_string(&table)?;
+    let mut file = fs::File::create(cache_file)?;
+    file.write_all(fields_json.as_bytes())?;
     Ok(())
 }
+
+// Refresh the schema cache, the first request of every operation. With `startup_retries`,
+// connection failures (DNS, TLS, refused or timed out) are retried up to that many times with
+// backoff instead of by send(), so the two retry counts never multiply; HTTP errors are not.
+async fn refresh_schema_cache(client: &AirtableClient, table_config: &TableConfig, cache_file: &str, startup_retries: u32) -> Result<(), Box<dyn std::error::Error>> {
+    let mut attempt = 0;
+    loop {
+        match cache_available_fields(client, &table_config.base_id, &table_config.table_name, cache_file, startup_retries == 0).await {
+            Err(err) if attempt < startup_retries && is_connection_error(err.as_ref()) => {
+                let delay = client.retry.delay(attempt, None);
+                eprintln!(
+                    "Could not reach Airtable ({}); retrying in {:.1}s ({} of {})",
+                    err,
+                    delay.as_secs_f64(),
+                    attempt + 1,
+                    startup_retries
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+// Whether an error came from failing to connect rather than from a response
+fn is_connection_error(err: &(dyn std::error::Error + 'static)) -> bool {
+    err.downcast_ref::<reqwest::Error>().is_some_and(|err| err.is_connect() || err.is_timeout())
+}
+
+// Longest list URL sent with GET before switching to the POST listRecords endpoint
+const MAX_URL_LENGTH: usize = 16_000;
+
+// JSON body for the POST listRecords endpoint from the equivalent GET query parameters
+fn list_body(params: &[(String, String)]) -> Value {
+    let mut body = Map::new();
+    for (name, value) in params {
+        match name.as_str() {
+            "fields[]" => {
+                let fields = body.entry("fields").or_insert_with(|| json!([]));
+                if let Some(fields) = fields.as_array_mut() {
+                    fields.push(json!(value));
+                }
+            }
+            _ if name.starts_with("sort[") => {
+                // sort[0][field] and sort[0][direction] become {"sort": [{"field": ..., "direction": ...}]}
+                let mut parts = name.trim_start_matches("sort[").split("][");
+                let index: usize = parts.next().and_then(|index| index.parse().ok()).unwrap_or_default();
+                let key = parts.next().unwrap_or_default().trim_end_matches(']');
+                let sort = body.entry("sort").or_insert_with(|| json!([]));
+                if let Some(sort) = sort.as_array_mut() {
+                    while sort.len() <= index {
+                        sort.push(json!({}));
+                    }
+                    sort[index][key] = json!(value);
+                }
+            }
+            "maxRecords" | "pageSize" => {
+                body.insert(name.clone(), value.parse::<u64>().map(Value::from).unwrap_or_else(|_| json!(value)));
+            }
+            _ => {
+                body.insert(name.clone(), json!(value));
+            }
+        }
+    }
+    Value::Object(body)
+}
+
+// Walks a table's records page by page, following Airtable's offset cursor
+struct RecordPager<'a> {
+    client: &'a AirtableClient,
+    url: String,
+    params: Vec<(String, String)>,
+    offset: Option<String>,
+    done: bool,
+    // Records fetched so far, for --progress-json
+    fetched: usize,
+}
+
+impl<'a> RecordPager<'a> {
+    fn new(client: &'a AirtableClient, url: String, params: Vec<(String, String)>) -> Self {
+        RecordPager {
+            client,
+            url,
+            params,
+            offset: None,
+            done: false,
+            fetched: 0,
+        }
+    }
+
+    async fn next_page(&mut self) -> Result<Option<Vec<Record>>, Box<dyn std::error::Error>> {
+        if self.done {
+            return Ok(None);
+        }
+
+        let mut params = self.params.clone();
+        if let Some(offset) = &self.offset {
+            params.push(("offset".to_string(), offset.clone()));
+        }
+        // Long formulas can push the URL past Airtable's 16k limit; the POST variant takes them in the body
+        let request = if reqwest::Url::parse_with_params(&self.url, &params)?.as_str().len() > MAX_URL_LENGTH {
+            self.client.post(format!("{}/listRecords", self.url)).json(&list_body(&params))
+        } else {
+            self.client.get(&self.url).query(&params)
+        };
+        let (status, text) = self.client.send(request).await?;
+        if !status.is_success() {
+            return Err(RauError::from_status(status, format!("Failed to list records. Status: {}, Response: {}", status, text)));
+        }
+
+        let page: RecordsResponse = self.client.parse(&text)?;
+        self.offset = page.offset;
+        self.done = self.offset.is_none();
+        self.fetched += page.records.len();
+        self.client.progress(json!({ "event": "page", "count": self.fetched }));
+        Ok(Some(page.records))
+    }
+}
+
+// Create a new record from the updatable fields of an existing one, optionally in another configured table
+async fn copy_record(client: &AirtableClient, config: &Settings, table_config: &TableConfig, updatable_fields: &[String], source_id: &str, to: Option<&String>) -> Result<(), Box<dyn std::error::Error>> {
+    let source_url = format!("{}/v0/{}/{}/{}", client.api_url, table_config.base_id, table_config.table_name, source_id);
+    let (status, text) = client.send(client.get(&source_url)).await?;
+    if !status.is_success() {
+        return Err(RauError::from_status(status, format!("Failed to query record. Status: {}, Response: {}", status, text)));
+    }
+    let source: Record = client.parse_record(&text)?;
+
+    // The destination's updatable fields decide what can be carried over
+    let destination = match to {
+        Some(name) => config.tables.get(name).ok_or_else(|| RauError::Usage(format!("Configuration '{}' not found in config", name)))?,
+        None => table_config,
+    };
+    let destination_fields: Vec<Field> = match to {
+        Some(name) => {
+            let table = fetch_available_fields(client, &destination.base_id, &destination.table_name, true).await?;
+            // An unknown table comes back with no ID and no fields, which would silently copy nothing
+            if table.id.is_empty() || table.fields.is_empty() {
+                return Err(RauError::NotFound(format!("No table '{}' with fields in base {} for configuration '{}'", destination.table_name, destination.base_id, name)).into());
+            }
+            table.fields
+        }
+        None => Vec::new(),
+    };
+    let destination_updatable: Vec<&str> = match to {
+        Some(_) => destination_fields
+            .iter()
+            .filter(|field| !is_computed_type(&field.field_type))
+            .map(|field| field.name.as_str())
+            .collect(),
+        None => updatable_fields.iter().map(String::as_str).collect(),
+    };
+
+    let mut copied = Map::new();
+    for (name, value) in source.fields.as_object().into_iter().flatten() {
+        if !updatable_fields.contains(name) {
+            continue;
+        }
+        if !destination_updatable.contains(&name.as_str()) {
+            eprintln!("Skipping {}: not an updatable field of the destination table", name);
+            continue;
+        }
+        copied.insert(name.clone(), copyable_value(value));
+    }
+
+    let create_data = json!({
+        "records": [
+            {
+                "fields": Value::Object(copied)
+            }
+        ]
+    });
+    let create_url = format!("{}/v0/{}/{}", client.api_url, destination.base_id, destination.table_name);
+    let request = client
+        .post(&create_url)
+        .header("Content-Type", "application/json")
+        .json(&create_data);
+    let (status, text) = client.send(request).await?;
+
+    if status.is_success() {
+        let created_records: RecordsResponse = client.parse(&text)?;
+        if let Some(new_record) = created_records.records.first() {
+            if !client.quiet_success {
+                eprintln!("Copied {} to new Record ID", source_id);
+                println!("{}", new_record.id);
+            }
+            client.records_affected.fetch_add(1, Ordering::SeqCst);
+            client.audit(destination, "create", &new_record.id, &create_data["records"][0]["fields"]);
+        } else {
+            return Err(format!("Failed to parse the response after copying the record: {}", text).into());
+        }
+    } else {
+        return Err(RauError::from_status(status, format!("Failed to copy record. Status: {}, Response: {}", status, text)));
+    }
+    Ok(())
+}
+
+// Attachments read back with generated IDs, sizes and thumbnails; only their URL and file name
+// can be written to a new record
+fn copyable_value(value: &Value) -> Value {
+    match value {
+        Value::Array(items) if !items.is_empty() && items.iter().all(|item| item.get("url").is_some() && item.get("filename").is_some()) => Value::Array(
+            items
+                .iter()
+                .map(|item| json!({ "url": item["url"], "filename": item["filename"] }))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+// Read a JSON object mapping Airtable field names to the names used in exports
+fn load_rename_map(path: &str, fields: &[Field], ignore_unknown: bool) -> Result<HashMap<String, String>, Box<dyn std::error::Error>> {
+    let renames: HashMap<String, String> = serde_json::from_str(&fs::read_to_string(path)?)?;
+    if !ignore_unknown {
+        if let Some(unknown) = renames.keys().find(|name| !fields.iter().any(|field| &field.name == *name)) {
+            return Err(format!("Rename map {} refers to unknown field '{}' (pass --ignore-unknown to allow)", path, unknown).into());
+        }
+    }
+    Ok(renames)
+}
+
+fn export_name<'a>(renames: &'a HashMap<String, String>, field_name: &'a str) -> &'a str {
+    renames.get(field_name).map(String::as_str).unwrap_or(field_name)
+}
+
+// SQLite column type for an Airtable field type. Computed fields get no declared type so
+// whatever their result is stored unchanged.
+fn sqlite_column_type(field_type: &str) -> &'static str {
+    match field_type {
+        "autoNumber" | "count" | "rating" | "checkbox" => "INTEGER",
+        "number" | "percent" | "currency" | "duration" => "REAL",
+        "formula" | "rollup" | "lookup" | "multipleLookupValues" => "",
+        _ => "TEXT",
+    }
+}
+
+fn sqlite_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+fn sqlite_value(value: Option<&Value>) -> rusqlite::types::Value {
+    use rusqlite::types::Value as Sql;
+    match value {
+        None | Some(Value::Null) => Sql::Null,
+        Some(Value::Bool(flag)) => Sql::Integer(i64::from(*flag)),
+        Some(Value::Number(number)) => match number.as_i64() {
+            Some(integer) => Sql::Integer(integer),
+            None => Sql::Real(number.as_f64().unwrap_or_default()),
+        },
+        Some(Value::String(text)) => Sql::Text(text.clone()),
+        Some(other) => Sql::Text(other.to_string()),
+    }
+}
+
+// Export every record of a table into a SQLite table mirroring its schema, keyed by record ID
+async fn export_sqlite(client: &AirtableClient, table_config: &TableConfig, fields: &[Field], renames: &HashMap<String, String>, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    // SQLite column names are case-insensitive, and `id` holds the record ID
+    let mut seen = vec!["id".to_string()];
+    for field in fields {
+        let name = export_name(renames, &field.name);
+        if seen.contains(&name.to_lowercase()) {
+            return Err(RauError::Usage(format!("Field '{}' would be exported as column '{}', which is already taken; map it to another name with --rename-map", field.name, name)).into());
+        }
+        seen.push(name.to_lowercase());
+    }
+
+    let mut conn = rusqlite::Connection::open(path)?;
+    let table = sqlite_identifier(&table_config.table_name);
+    let column_names: Vec<String> = fields.iter().map(|field| sqlite_identifier(export_name(renames, &field.name))).collect();
+    let columns: Vec<String> = fields
+        .iter()
+        .zip(&column_names)
+        .map(|(field, column)| format!("{} {}", column, sqlite_column_type(&field.field_type)).trim_end().to_string())
+        .collect();
+
+    // A table from an earlier export whose columns no longer match the schema is recreated, since
+    // every record is exported again anyway
+    let expected: Vec<(String, String)> = std::iter::once(("id".to_string(), "TEXT".to_string()))
+        .chain(fields.iter().map(|field| (export_name(renames, &field.name).to_string(), sqlite_column_type(&field.field_type).to_string())))
+        .collect();
+    let existing: Vec<(String, String)> = conn
+        .prepare("SELECT name, type FROM pragma_table_info(?1)")?
+        .query_map([&table_config.table_name], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<_, _>>()?;
+    if !existing.is_empty() && existing != expected {
+        if !client.quiet_success {
+            eprintln!("The columns of {} no longer match the schema, recreating it", table_config.table_name);
+        }
+        conn.execute(&format!("DROP TABLE {}", table), [])?;
+    }
+    conn.execute(&format!("CREATE TABLE IF NOT EXISTS {} (\"id\" TEXT PRIMARY KEY, {})", table, columns.join(", ")), [])?;
+
+    let placeholders = vec!["?"; fields.len() + 1].join(", ");
+    let insert = format!("INSERT OR REPLACE INTO {} (\"id\", {}) VALUES ({})", table, column_names.join(", "), placeholders);
+
+    let records_url = format!("{}/v0/{}/{}", client.api_url, table_config.base_id, table_config.table_name);
+    let mut pager = RecordPager::new(client, records_url, Vec::new());
+    let mut exported = 0;
+    while let Some(records) = pager.next_page().await? {
+        let tx = conn.transaction()?;
+        {
+            let mut stmt = tx.prepare(&insert)?;
+            for record in &records {
+                let mut row = vec![rusqlite::types::Value::Text(record.id.clone())];
+                row.extend(fields.iter().map(|field| sqlite_value(record.fields.get(&field.name))));
+                stmt.execute(rusqlite::params_from_iter(row))?;
+            }
+        }
+        tx.commit()?;
+        exported += records.len();
+
+        if interrupted() {
+            return Err(RauError::Interrupted(format!("Interrupted after exporting {} records", exported)).into());
+        }
+    }
+
+    if !client.quiet_success {
+        eprintln!("Exported {} records to {}", exported, path);
+    }
+    client.records_affected.fetch_add(exported, Ordering::SeqCst);
+    Ok(())
+}
+
+// Export every record of a table as CSV, to a file or stdout with `-`. Linked record fields named
+// with --expand-links are written as the linked records' primary field values instead of their IDs.
+async fn export_csv(client: &AirtableClient, table_config: &TableConfig, fields: &[Field], renames: &HashMap<String, String>, expand: &[&str], path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let links = linked_primary_fields(client, &table_config.base_id, fields, expand).await?;
+    let output: Box<dyn io::Write> = match path {
+        "-" => Box::new(io::stdout()),
+        path => Box::new(fs::File::create(path).map_err(|err| format!("Failed to create {}: {}", path, err))?),
+    };
+    let mut writer = csv::Writer::from_writer(output);
+    writer.write_record(std::iter::once("id").chain(fields.iter().map(|field| export_name(renames, &field.name))))?;
+
+    // Primary field values of linked records, looked up once per record for the whole export
+    let mut names: HashMap<String, String> = HashMap::new();
+    let records_url = format!("{}/v0/{}/{}", client.api_url, table_config.base_id, table_config.table_name);
+    let mut pager = RecordPager::new(client, records_url, Vec::new());
+    let mut exported = 0;
+    while let Some(records) = pager.next_page().await? {
+        for (field_name, (table_id, primary)) in &links {
+            let mut ids: Vec<String> = records
+                .iter()
+                .filter_map(|record| record.fields.get(field_name).and_then(Value::as_array))
+                .flatten()
+                .filter_map(Value::as_str)
+                .filter(|id| !names.contains_key(*id))
+                .map(str::to_string)
+                .collect();
+            ids.sort();
+            ids.dedup();
+            names.extend(primary_values(client, &table_config.base_id, table_id, primary, &ids).await?);
+        }
+
+        for record in &records {
+            let mut row = vec![record.id.clone()];
+            for field in fields {
+                let value = record.fields.get(&field.name);
+                let cell = match (links.contains_key(&field.name), value) {
+                    (true, Some(Value::Array(ids))) => ids
+                        .iter()
+                        .filter_map(Value::as_str)
+                        .map(|id| names.get(id).map(String::as_str).unwrap_or(id))
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    _ => csv_text(value),
+                };
+                row.push(cell);
+            }
+            writer.write_record(&row)?;
+        }
+        exported += records.len();
+
+        if interrupted() {
+            writer.flush()?;
+            return Err(RauError::Interrupted(format!("Interrupted after exporting {} records", exported)).into());
+        }
+    }
+    writer.flush()?;
+
+    if !client.quiet_success {
+        eprintln!("Exported {} records to {}", exported, path);
+    }
+    client.records_affected.fetch_add(exported, Ordering::SeqCst);
+    Ok(())
+}
+
+// A value as CSV text: lists of plain values comma-separated, as `import` reads them back, and
+// other arrays and objects as JSON
+fn csv_text(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(items)) if items.iter().all(|item| item.is_string() || item.is_number()) => {
+            items.iter().map(|item| item.as_str().map(str::to_string).unwrap_or_else(|| item.to_string())).collect::<Vec<_>>().join(", ")
+        }
+        Some(other) => other.to_string(),
+    }
+}
+
+// For each linked record field to expand, the ID of the table it links to and that table's primary field
+async fn linked_primary_fields(client: &AirtableClient, base_id: &str, fields: &[Field], expand: &[&str]) -> Result<HashMap<String, (String, String)>, Box<dyn std::error::Error>> {
+    let mut links = HashMap::new();
+    if expand.is_empty() {
+        return Ok(links);
+    }
+    let url = format!("{}/v0/meta/bases/{}/tables", client.api_url, base_id);
+    let (status, text) = client.send(client.get(&url)).await?;
+    if !status.is_success() {
+        return Err(RauError::from_status(status, format!("Failed to fetch schema. Status: {}, Response: {}", status, text)));
+    }
+    let tables: TablesResponse = serde_json::from_str(&text)?;
+
+    for name in expand {
+        let field = fields
+            .iter()
+            .find(|field| field.name == *name)
+            .ok_or_else(|| format!("Field '{}' not found in schema", name))?;
+        if field.field_type != "multipleRecordLinks" {
+            return Err(format!("Field '{}' is a {} field, not a linked record field", name, field.field_type).into());
+        }
+        let table_id = field
+            .options
+            .as_ref()
+            .and_then(|options| options.get("linkedTableId"))
+            .and_then(Value::as_str)
+            .ok_or_else(|| format!("The schema does not say which table '{}' links to", name))?;
+        let primary = tables
+            .tables
+            .iter()
+            .find(|table| table.id == table_id)
+            .and_then(|table| table.fields.iter().find(|field| field.id == table.primary_field_id))
+            .ok_or_else(|| format!("Could not find the primary field of the table '{}' links to", name))?;
+        links.insert(name.to_string(), (table_id.to_string(), primary.name.clone()));
+    }
+    Ok(links)
+}
+
+// The primary field value of each record, fetched IDS_PER_REQUEST at a time. Records that no
+// longer exist are left out.
+async fn primary_values(client: &AirtableClient, base_id: &str, table_id: &str, primary: &str, ids: &[String]) -> Result<HashMap<String, String>, Box<dyn std::error::Error>> {
+    let records_url = format!("{}/v0/{}/{}", client.api_url, base_id, table_id);
+    let mut values = HashMap::new();
+    for chunk in ids.chunks(IDS_PER_REQUEST) {
+        let clauses: Vec<String> = chunk.iter().map(|id| format!("RECORD_ID()={}", formula_string(id))).collect();
+        let params = vec![
+            ("filterByFormula".to_string(), format!("OR({})", clauses.join(", "))),
+            ("fields[]".to_string(), primary.to_string()),
+        ];
+        let mut pager = RecordPager::new(client, records_url.clone(), params);
+        while let Some(page) = pager.next_page().await? {
+            for record in page {
+                let value = csv_text(record.fields.get(primary));
+                values.insert(record.id, value);
+            }
+        }
+    }
+    Ok(values)
+}
+
+// Download every file in an attachment field of the matching records into `dir`, as
+// <dir>/<record_id>/<filename>, up to --max-concurrency files at a time
+async fn download_attachments(client: &AirtableClient, table_config: &TableConfig, fields: &[Field], field_name: &str, dir: &str, opts: &Options<'_>) -> Result<(), Box<dyn std::error::Error>> {
+    let field = fields
+        .iter()
+        .find(|field| field.name == field_name)
+        .ok_or_else(|| format!("Field '{}' not found in schema", field_name))?;
+    if field.field_type != "multipleAttachments" {
+        return Err(format!("Field '{}' is a {} field, not an attachment field", field_name, field.field_type).into());
+    }
+
+    // Every matching record, as for a backup, fetching only the attachment field
+    let mut params: Vec<(String, String)> = list_params(opts).into_iter().filter(|(name, _)| name != "maxRecords").collect();
+    params.push(("fields[]".to_string(), field_name.to_string()));
+    let records_url = format!("{}/v0/{}/{}", client.api_url, table_config.base_id, table_config.table_name);
+    let mut pager = RecordPager::new(client, records_url, params);
+    let (mut downloaded, mut failed) = (0, 0);
+    while let Some(records) = pager.next_page().await? {
+        let mut files = Vec::new();
+        for record in &records {
+            let attachments = record.fields.get(field_name).and_then(Value::as_array).cloned().unwrap_or_default();
+            let mut names = std::collections::HashSet::new();
+            for attachment in attachments {
+                let Some(url) = attachment.get("url").and_then(Value::as_str) else {
+                    continue;
+                };
+                let id = attachment.get("id").and_then(Value::as_str).unwrap_or_default();
+                let mut filename = attachment.get("filename").and_then(Value::as_str).unwrap_or(id).replace(['/', '\\'], "_");
+                if filename.is_empty() || filename == "." || filename == ".." {
+                    filename = format!("attachment-{}", names.len() + 1);
+                }
+                // Two files of a record with the same name keep both, the later prefixed with its attachment ID
+                if !names.insert(filename.clone()) {
+                    filename = format!("{}-{}", id, filename);
+                }
+                let path = std::path::Path::new(dir).join(&record.id).join(filename);
+                files.push((record.id.clone(), url.to_string(), path));
+            }
+        }
+
+        let results: Vec<_> = stream::iter(files)
+            .map(|(record_id, url, path)| async move {
+                let result = download_file(client, &url, &path).await;
+                (record_id, path, result)
+            })
+            .buffer_unordered(opts.max_concurrency.max(1))
+            .collect()
+            .await;
+        for (record_id, path, result) in results {
+            match result {
+                Ok(()) => downloaded += 1,
+                Err(err) => {
+                    eprintln!("Failed to download {} of {}: {}", path.display(), record_id, err);
+                    failed += 1;
+                }
+            }
+        }
+
+        if interrupted() {
+            return Err(RauError::Interrupted(format!("Interrupted after downloading {} files", downloaded)).into());
+        }
+    }
+
+    if !client.quiet_success {
+        eprintln!("Downloaded {} files to {}, {} failed", downloaded, dir, failed);
+    }
+    if failed > 0 {
+        return Err(format!("{} attachment(s) failed to download", failed).into());
+    }
+    Ok(())
+}
+
+// Save one attachment. Its URL is a signed link to Airtable's file host, so it is fetched without
+// the API key or extra headers.
+async fn download_file(client: &AirtableClient, url: &str, path: &std::path::Path) -> Result<(), Box<dyn std::error::Error>> {
+    client.pace().await?;
+    let resp = client.http.get(url).send().await?;
+    if !resp.status().is_success() {
+        return Err(format!("Status: {}", resp.status()).into());
+    }
+    let bytes = resp.bytes().await?;
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    tokio::fs::write(path, &bytes).await?;
+    Ok(())
+}
+
+fn read_cached_table(cache_file: &str) -> io::Result<Table> {
+    let fields_json = fs::read_to_string(cache_file)?;
+    let table: Table = serde_json::from_str(&fields_json)?;
+    Ok(table)
+}
+
+// Rename a field through the meta API, then drop the schema cache so the next run fetches the new name
+async fn rename_field(client: &AirtableClient, table_config: &TableConfig, table_id: &str, fields: &[Field], cache_file: &str, old_name: &str, new_name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let field = fields
+        .iter()
+        .find(|field| field.name == old_name)
+        .ok_or_else(|| format!("Field '{}' not found in schema", old_name))?;
+    if fields.iter().any(|field| field.name == new_name) {
+        return Err(format!("Table {} already has a field named '{}'", table_config.table_name, new_name).into());
+    }
+    if table_id.is_empty() || field.id.is_empty() {
+        return Err(format!("The schema has no table or field ID for '{}'", old_name).into());
+    }
+
+    let url = format!("{}/v0/meta/bases/{}/tables/{}/fields/{}", client.api_url, table_config.base_id, table_id, field.id);
+    let (status, text) = client.send(client.patch(&url).json(&json!({ "name": new_name }))).await?;
+    if !status.is_success() {
+        // The schema may be stale, e.g. someone else added the field since it was read
+        if text.contains("DUPLICATE") {
+            return Err(format!("Table {} already has a field named '{}'", table_config.table_name, new_name).into());
+        }
+        return Err(RauError::from_status(status, format!("Failed to rename field. Status: {}, Response: {}", status, text)));
+    }
+
+    match fs::remove_file(cache_file) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => eprintln!("Warning: could not remove schema cache {}: {}", cache_file, err),
+        _ => {}
+    }
+    if !client.quiet_success {
+        println!("Renamed field '{}' to '{}'", old_name, new_name);
+    }
+    Ok(())
+}
+
+// Read a schema file given with --assume-schema: a table as stored in the schema cache, or just
+// its array of fields
+fn load_assumed_schema(path: &str, table_config: &TableConfig) -> Result<Table, Box<dyn std::error::Error>> {
+    let text = fs::read_to_string(path).map_err(|err| format!("Failed to read schema file {}: {}", path, err))?;
+    if let Ok(fields) = serde_json::from_str::<Vec<Field>>(&text) {
+        return Ok(Table {
+            id: String::new(),
+            name: table_config.table_name.clone(),
+            fields,
+            views: Vec::new(),
+            primary_field_id: String::new(),
+        });
+    }
+    serde_json::from_str(&text).map_err(|err| format!("Invalid schema file {}: {}", path, err).into())
+}
+
+// Whether a field argument sets a value rather than naming a field to query
+fn is_update_arg(field: &str) -> bool {
+    field.contains('=') || parse_inline_attachment(field).is_some()
+}
+
+// Split a `field@base64:<data>` argument into the attachment field name and its base64 content
+fn parse_inline_attachment(field: &str) -> Option<(&str, &str)> {
+    let (name, data) = field.split_once("@base64:")?;
+    if name.is_empty() || name.contains('=') {
+        return None;
+    }
+    Some((name, data))
+}
+
+// Split a `field+=value` or `field-=value` argument into the field name, whether it removes, and the value
+fn parse_array_edit(field: &str) -> Option<(&str, bool, &str)> {
+    let (key, value) = field.split_once('=')?;
+    if let Some(name) = key.strip_suffix('+') {
+        Some((name, false, value))
+    } else {
+        key.strip_suffix('-').map(|name| (name, true, value))
+    }
+}
+
+// Split a `field~=url` argument into the attachment field name and the URL of an already-hosted file
+fn parse_attachment_url(field: &str) -> Option<(&str, &str)> {
+    let (key, url) = field.split_once('=')?;
+    key.strip_suffix('~').filter(|name| !name.is_empty()).map(|name| (name, url))
+}
+
+// Add a `field=value` or `field~=url` argument to the fields being written. `~=` wraps the URL as
+// `[{"url": ...}]`, and repeating it for the same field adds further attachments.
+fn add_field_assignment(fields: &mut Map<String, Value>, field: &str) -> Result<(), String> {
+    if let Some((name, url)) = parse_attachment_url(field) {
+        let attachment = json!({ "url": url });
+        match fields.get_mut(name).and_then(Value::as_array_mut) {
+            Some(attachments) => attachments.push(attachment),
+            None => {
+                fields.insert(name.to_string(), json!([attachment]));
+            }
+        }
+        return Ok(());
+    }
+    let (name, value) = field.split_once('=').ok_or_else(|| format!("Invalid field format: {}", field))?;
+    fields.insert(name.to_string(), parse_json_string(value));
+    Ok(())
+}
+
+// Add values to (or remove them from) a multi-value field's current array, without duplicates.
+// An empty or missing current value is treated as an empty array.
+fn merge_array_value(current: Option<&Value>, values: Value, remove: bool) -> Value {
+    let mut merged: Vec<Value> = match current {
+        Some(Value::Array(items)) => items.clone(),
+        Some(Value::Null) | None => Vec::new(),
+        Some(other) => vec![other.clone()],
+    };
+    let values = match values {
+        Value::Array(items) => items,
+        other => vec![other],
+    };
+
+    if remove {
+        merged.retain(|item| !values.contains(item));
+    } else {
+        for value in values {
+            if !merged.contains(&value) {
+                merged.push(value);
+            }
+        }
+    }
+    Value::Array(merged)
+}
+
+// The multi-value fields among `fields` that --merge-arrays merges into current values
+fn merged_array_fields(available_fields: &[Field], fields: &Map<String, Value>, merge_arrays: bool) -> Vec<String> {
+    if !merge_arrays {
+        return Vec::new();
+    }
+    available_fields
+        .iter()
+        .filter(|field| is_array_type(&field.field_type) && fields.contains_key(&field.name))
+        .map(|field| field.name.clone())
+        .collect()
+}
+
+// Replace the values of `merged_fields` with their merge into the record's current values
+fn merge_into_current(fields: &mut Map<String, Value>, merged_fields: &[String], record: &Record) {
+    for name in merged_fields {
+        if let Some(values) = fields.remove(name) {
+            fields.insert(name.clone(), merge_array_value(record.fields.get(name), values, false));
+        }
+    }
+}
+
+// Upload base64 content to an attachment field through the content upload endpoint
+async fn upload_attachment(client: &AirtableClient, base_id: &str, record_id: &str, field_name: &str, content_type: &str, filename: &str, data: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let mut upload_url = reqwest::Url::parse("https://content.airtable.com/v0/")?;
+    upload_url
+        .path_segments_mut()
+        .map_err(|_| "Invalid upload URL")?
+        .pop_if_empty()
+        .extend(&[base_id, record_id, field_name, "uploadAttachment"]);
+
+    let upload_data = json!({
+        "contentType": content_type,
+        "file": data,
+        "filename": filename
+    });
+
+    let request = client
+        .post(upload_url)
+        .header("Content-Type", "application/json")
+        .json(&upload_data);
+    let (status, text) = client.send(request).await?;
+
+    if !status.is_success() {
+        return Err(RauError::from_status(status, format!("Failed to upload attachment to {}. Status: {}, Response: {}", field_name, status, text)));
+    }
+    if !client.quiet_success {
+        println!("Uploaded Attachment to {}", field_name);
+    }
+    Ok(())
+}
+
+// The Airtable web URL of a record, which needs the table ID rather than its name
+fn record_url(base_id: &str, table_id: &str, record_id: &str) -> Option<String> {
+    if table_id.is_empty() {
+        eprintln!("Cannot build the record URL: table ID is unknown");
+        return None;
+    }
+    Some(format!("https://airtable.com/{}/{}/{}", base_id, table_id, record_id))
+}
+
+// Print the web URL of a created or updated record
+fn print_record_url(base_id: &str, table_id: &str, record_id: &str) {
+    if let Some(url) = record_url(base_id, table_id, record_id) {
+        print_url(&url);
+    }
+}
+
+// Print a record URL in text output, labelled on stderr so stdout holds just the URL
+fn print_url(url: &str) {
+    eprint!("URL: ");
+    println!("{}", url);
+}
+
+// Render a record's fields on one line as `field=value; field2=value2`, cutting values to `width` characters
+// and noting their full length
+fn oneline_record(record: &Record, width: usize) -> String {
+    record
+        .fields
+        .as_object()
+        .into_iter()
+        .flatten()
+        .map(|(field, value)| {
+            let text = match value {
+                Value::String(text) => text.replace('\n', " "),
+                other => other.to_string(),
+            };
+            format!("{}={}", field, truncate_for_display(&text, Some(width)))
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+// Cut text longer than `max` characters, noting its full length
+fn truncate_for_display(text: &str, max: Option<usize>) -> String {
+    let length = text.chars().count();
+    match max {
+        Some(max) if length > max => format!("{}… ({} chars)", text.chars().take(max).collect::<String>(), length),
+        _ => text.to_string(),
+    }
+}
+
+// Render a field value for text output, truncating long strings when --max-field-length is set
+fn display_value(value: &Value, max: Option<usize>) -> String {
+    match value {
+        Value::String(text) => Value::String(truncate_for_display(text, max)).to_string(),
+        other => other.to_string(),
+    }
+}
+
+// Escape text for a Markdown table cell
+fn markdown_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', "<br>")
+}
+
+// Field types whose values Airtable computes, which cannot be written
+fn is_computed_type(field_type: &str) -> bool {
+    matches!(
+        field_type,
+        "computed"
+            | "formula"
+            | "rollup"
+            | "lookup"
+            | "multipleLookupValues"
+            | "count"
+            | "autoNumber"
+            | "createdTime"
+            | "lastModifiedTime"
+            | "createdBy"
+            | "lastModifiedBy"
+            | "button"
+    )
+}
+
+// Drop computed fields from a record, leaving the ones that can be edited. Fields missing from the
+// schema are kept.
+fn remove_computed_fields(record: &mut Record, fields: &[Field]) {
+    if let Some(values) = record.fields.as_object_mut() {
+        values.retain(|name, _| !fields.iter().any(|field| &field.name == name && is_computed_type(&field.field_type)));
+    }
+}
+
+// Field types whose value is an array of items, merged rather than replaced under --merge-arrays
+fn is_array_type(field_type: &str) -> bool {
+    matches!(field_type, "multipleSelects" | "multipleRecordLinks" | "multipleAttachments" | "multipleCollaborators")
+}
+
+// How two records differ: each differing field's values as `{"a": ..., "b": ...}`, and with
+// --show-same the fields they share
+#[derive(Serialize)]
+struct RecordDiff {
+    #[serde(rename = "a")]
+    id_a: String,
+    #[serde(rename = "b")]
+    id_b: String,
+    different: Map<String, Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    same: Option<Map<String, Value>>,
+    #[serde(skip)]
+    same_count: usize,
+}
+
+// Fetch two records and print the fields that differ between them, and with --show-same the
+// ones they share. Missing, null, "" and [] count as the same empty value.
+async fn diff_records(client: &AirtableClient, table_config: &TableConfig, id_a: &str, id_b: &str, opts: &Options<'_>) -> Result<(), Box<dyn std::error::Error>> {
+    let records_url = format!("{}/v0/{}/{}", client.api_url, table_config.base_id, table_config.table_name);
+    let mut records = Vec::new();
+    for id in [id_a, id_b] {
+        let (status, text) = client.send(client.get(format!("{}/{}", records_url, id))).await?;
+        if !status.is_success() {
+            return Err(RauError::from_status(status, format!("Failed to query record {}. Status: {}, Response: {}", id, status, text)));
+        }
+        records.push(client.parse_record(&text)?);
+    }
+    let (a, b) = (&records[0].fields, &records[1].fields);
+
+    let mut names: Vec<&String> = a.as_object().into_iter().flatten().chain(b.as_object().into_iter().flatten()).map(|(name, _)| name).collect();
+    names.sort();
+    names.dedup();
+    let mut different = Map::new();
+    let mut same = Map::new();
+    for name in names {
+        let (value_a, value_b) = (a.get(name), b.get(name));
+        let is_same = match value_a {
+            Some(value_a) => same_field_value(value_a, value_b),
+            None => value_b.is_none_or(|value_b| same_field_value(value_b, None)),
+        };
+        if is_same {
+            same.insert(name.clone(), value_a.or(value_b).cloned().unwrap_or(Value::Null));
+        } else {
+            different.insert(name.clone(), json!({ "a": value_a, "b": value_b }));
+        }
+    }
+
+    let diff = RecordDiff {
+        id_a: id_a.to_string(),
+        id_b: id_b.to_string(),
+        different,
+        same_count: same.len(),
+        same: Some(same).filter(|_| opts.show_same),
+    };
+    Ok(opts.format.render_diff(&diff, opts)?)
+}
+
+// Rust source for a serde struct matching a table's records, with an enum per select field.
+// Every field is an Option because Airtable omits empty values.
+fn rust_codegen(table_name: &str, fields: &[Field]) -> String {
+    let struct_name = rust_identifier(table_name, true);
+    let mut enums = String::new();
+    let mut members = String::new();
+    let mut used_types = vec![struct_name.clone()];
+    let mut used_members = Vec::new();
+    for field in fields {
+        let choices: Vec<&Value> = field
+            .options
+            .as_ref()
+            .and_then(|options| options.get("choices"))
+            .and_then(Value::as_array)
+            .map(|choices| choices.iter().collect())
+            .unwrap_or_default();
+        let rust_type = match field.field_type.as_str() {
+            "singleSelect" | "multipleSelects" if !choices.is_empty() => {
+                let name = unique_identifier(rust_identifier(&field.name, true), &mut used_types);
+                enums.push_str(&rust_select_enum(&name, &choices));
+                if field.field_type == "singleSelect" {
+                    name
+                } else {
+                    format!("Vec<{}>", name)
+                }
+            }
+            "number" | "currency" | "percent" | "duration" => "f64".to_string(),
+            "rating" | "autoNumber" | "count" => "i64".to_string(),
+            "checkbox" => "bool".to_string(),
+            "multipleRecordLinks" | "multipleSelects" => "Vec<String>".to_string(),
+            "multipleAttachments" => "Vec<serde_json::Value>".to_string(),
+            "formula" | "rollup" | "lookup" | "multipleLookupValues" => "serde_json::Value".to_string(),
+            _ => "String".to_string(),
+        };
+
+        members.push_str(&format!("    /// {} ({})\n", field.name, field.field_type));
+        if field.options.as_ref().and_then(|options| options.get("isReversed")).and_then(Value::as_bool) == Some(true) {
+            members.push_str("    /// Linked records are shown newest first in Airtable (isReversed)\n");
+        }
+        members.push_str(&format!(
+            "    #[serde(rename = {:?}, default, skip_serializing_if = \"Option::is_none\")]\n",
+            field.name
+        ));
+        let member = unique_identifier(rust_identifier(&field.name, false), &mut used_members);
+        members.push_str(&format!("    pub {}: Option<{}>,\n", member, rust_type));
+    }
+
+    format!(
+        "// Generated by `rau <config> codegen` from the {} schema\nuse serde::{{Deserialize, Serialize}};\n\n#[derive(Debug, Clone, Default, Serialize, Deserialize)]\npub struct {} {{\n{}}}\n{}",
+        table_name, struct_name, members, enums
+    )
+}
+
+// A serde enum with one variant per select choice, renamed where the choice is not a valid
+// variant name as is. Choice colors are kept as doc comments.
+fn rust_select_enum(name: &str, choices: &[&Value]) -> String {
+    let mut variants = String::new();
+    let mut used = Vec::new();
+    for choice in choices {
+        let choice_name = choice.get("name").and_then(Value::as_str).unwrap_or_default();
+        if let Some(color) = choice.get("color").and_then(Value::as_str) {
+            variants.push_str(&format!("    /// Color: {}\n", color));
+        }
+        let variant = unique_identifier(rust_identifier(choice_name, true), &mut used);
+        if variant != choice_name {
+            variants.push_str(&format!("    #[serde(rename = {:?})]\n", choice_name));
+        }
+        variants.push_str(&format!("    {},\n", variant));
+    }
+    format!(
+        "\n#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]\npub enum {} {{\n{}}}\n",
+        name, variants
+    )
+}
+
+// A Rust identifier from arbitrary text: PascalCase for types and variants, snake_case otherwise
+fn rust_identifier(text: &str, pascal: bool) -> String {
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            if pascal {
+                let mut chars = word.chars();
+                chars.next().map(|first| first.to_uppercase().chain(chars).collect()).unwrap_or_default()
+            } else {
+                word.to_lowercase()
+            }
+        })
+        .collect();
+    let identifier = if pascal { words.concat() } else { words.join("_") };
+    match identifier.chars().next() {
+        None => if pascal { "Empty" } else { "field" }.to_string(),
+        Some(first) if first.is_numeric() => format!("{}{}", if pascal { "V" } else { "field_" }, identifier),
+        _ if !pascal && RUST_KEYWORDS.contains(&identifier.as_str()) => format!("{}_", identifier),
+        _ if pascal && identifier == "Self" => "SelfValue".to_string(),
+        _ => identifier,
+    }
+}
+
+const RUST_KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern", "false", "fn", "for", "if", "impl", "in",
+    "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "self", "static", "struct", "super", "trait", "true", "type",
+    "unsafe", "use", "where", "while",
+];
+
+// Append a number to an identifier already in `used`, then record it
+fn unique_identifier(identifier: String, used: &mut Vec<String>) -> String {
+    let mut candidate = identifier.clone();
+    let mut suffix = 2;
+    while used.contains(&candidate) {
+        candidate = format!("{}{}", identifier, suffix);
+        suffix += 1;
+    }
+    used.push(candidate.clone());
+    candidate
+}
+
+// JSON Schema (draft 2020-12) for the fields of a record, as written to or read from the API
+fn json_schema(table_name: &str, fields: &[Field], required: &[&str]) -> Value {
+    let mut properties = Map::new();
+    for field in fields {
+        let mut property = field_json_schema(field);
+        if let Some(description) = &field.description {
+            property["description"] = json!(description);
+        }
+        if is_computed_type(&field.field_type) {
+            property["readOnly"] = json!(true);
+        }
+        properties.insert(field.name.clone(), property);
+    }
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": table_name,
+        "type": "object",
+        "properties": properties,
+        "required": required,
+    })
+}
+
+// Schema for one field's value, with select choices as enums
+fn field_json_schema(field: &Field) -> Value {
+    let choices: Vec<Value> = field
+        .options
+        .as_ref()
+        .and_then(|options| options.get("choices"))
+        .and_then(|choices| choices.as_array())
+        .map(|choices| choices.iter().filter_map(|choice| choice.get("name").cloned()).collect())
+        .unwrap_or_default();
+    match field.field_type.as_str() {
+        "singleLineText" | "multilineText" | "richText" | "phoneNumber" | "barcode" => json!({"type": "string"}),
+        "email" => json!({"type": "string", "format": "email"}),
+        "url" => json!({"type": "string", "format": "uri"}),
+        "number" | "currency" | "percent" | "duration" => json!({"type": "number"}),
+        "rating" | "autoNumber" | "count" => json!({"type": "integer"}),
+        "checkbox" => json!({"type": "boolean"}),
+        "date" => json!({"type": "string", "format": "date"}),
+        "dateTime" | "createdTime" | "lastModifiedTime" => json!({"type": "string", "format": "date-time"}),
+        "singleSelect" => json!({"type": "string", "enum": choices}),
+        "multipleSelects" => json!({"type": "array", "items": {"type": "string", "enum": choices}}),
+        "multipleRecordLinks" => json!({"type": "array", "items": {"type": "string", "pattern": "^rec"}}),
+        "multipleAttachments" => json!({
+            "type": "array",
+            "items": {
+                "type": "object",
+                "properties": {"url": {"type": "string", "format": "uri"}, "filename": {"type": "string"}},
+                "required": ["url"],
+            },
+        }),
+        // Formulas, rollups, lookups and unknown types can hold any value
+        _ => json!({}),
+    }
+}
+
+// Whether a value Airtable returned matches what was written. Airtable omits empty fields and
+// unchecked checkboxes, so a missing value matches null, "", [] and false. Numbers compare by
+// value, so 3 matches 3.0.
+fn same_field_value(sent: &Value, stored: Option<&Value>) -> bool {
+    match (sent, stored) {
+        (Value::Number(sent), Some(Value::Number(stored))) => sent.as_f64() == stored.as_f64(),
+        (_, Some(stored)) => sent == stored,
+        (Value::Null, None) | (Value::Bool(false), None) => true,
+        (Value::String(text), None) => text.is_empty(),
+        (Value::Array(items), None) => items.is_empty(),
+        (_, None) => false,
+    }
+}
+
+// Helper function to parse JSON strings into JSON objects
+fn parse_json_string(value: &str) -> serde_json::Value {
+    serde_json::from_str(value).unwrap_or_else(|_| json!(value))
+}
+
+fn build_cli() -> Command {
+    Command::new("Airtable CLI")
+        .version("1.0")
+        .about("Update or query Airtable records from the CLI")
+        .arg(
+            Arg::new("config")
+                .help("The name of the configuration to use")
+                .required(true)
+                .index(1),
+        )
+        .arg(
+            Arg::new("record_id")
+                .help("The ID of the record to update or query")
+                .required(false)
+                .index(2),
+        )
+        .arg(
+            Arg::new("fields")
+                .help("Fields to update in key=value format or fields to query for their values")
+                .index(3)
+                .num_args(1..)
+                .require_equals(false)
+                .required(false),
+        )
+        .arg(
+            Arg::new("schema")
+                .short('s')
+                .long("schema")
+                .action(ArgAction::SetTrue)
+                .help("Output the schema")
+                .conflicts_with("fields_flag"),
+        )
+        .arg(
+            Arg::new("fields_flag")
+                .short('f')
+                .long("fields")
+                .action(ArgAction::SetTrue)
+                .help("Output the fields")
+                .conflicts_with("schema"),
+        )
+        .arg(
+            Arg::new("recent")
+                .short('r')
+                .long("recent")
+                .action(ArgAction::SetTrue)
+                .help("Output the 100 most recent record IDs and their names"),
+        )
+        .arg(
+            Arg::new("primary_only")
+                .long("primary-only")
+                .action(ArgAction::SetTrue)
+                .help("List only the values of the table's primary field, one per line"),
+        )
+        .arg(
+            Arg::new("glob")
+                .short('g')
+                .long("glob")
+                .action(ArgAction::SetTrue)
+                .help("Treat the configuration name as a glob and run against every matching table"),
+        )
+        .arg(
+            Arg::new("yes")
+                .short('y')
+                .long("yes")
+                .action(ArgAction::SetTrue)
+                .help("Confirm operations that write to more than one table, raw requests other than GET, and set"),
+        )
+        .arg(
+            Arg::new("only_changed")
+                .long("only-changed")
+                .action(ArgAction::SetTrue)
+                .help("Read current values first and skip writes that would not change anything"),
+        )
+        .arg(
+            Arg::new("field")
+                .long("field")
+                .value_name("FIELD")
+                .help("download-attachments: attachment field to download"),
+        )
+        .arg(
+            Arg::new("dir")
+                .long("dir")
+                .value_name("DIR")
+                .help("download-attachments: directory to save files in, one subdirectory per record"),
+        )
+        .arg(
+            Arg::new("if_match")
+                .long("if-match")
+                .value_name("JSON")
+                .help("Only update the record if its fields currently hold these values, e.g. '{\"Status\":\"Todo\"}'"),
+        )
+        .arg(
+            Arg::new("merge_arrays")
+                .long("merge-arrays")
+                .action(ArgAction::SetTrue)
+                .help("Merge values written to multi-value fields into their current values instead of replacing them"),
+        )
+        .arg(
+            Arg::new("replace_arrays")
+                .long("replace-arrays")
+                .action(ArgAction::SetTrue)
+                .help("Replace multi-value fields with the values written (the default; overrides --merge-arrays)"),
+        )
+        .arg(
+            Arg::new("stdin")
+                .long("stdin")
+                .action(ArgAction::SetTrue)
+                .help("import: read CSV from stdin"),
+        )
+        .arg(
+            Arg::new("dry_run")
+                .long("dry-run")
+                .action(ArgAction::SetTrue)
+                .help("set: list the records that would be updated without changing them"),
+        )
+        .arg(
+            Arg::new("no_env")
+                .long("no-env")
+                .action(ArgAction::SetTrue)
+                .help("Ignore AIRTABLE_* environment overrides and use only the config file"),
+        )
+        .arg(
+            Arg::new("config_format")
+                .long("config-format")
+                .value_parser(["toml", "yaml", "json"])
+                .help("Format of the config file, instead of detecting it from the file extension (or set RAU_CONFIG_FORMAT)"),
+        )
+        .arg(
+            Arg::new("record_url")
+                .short('u')
+                .long("record-url")
+                .action(ArgAction::SetTrue)
+                .help("Also print the Airtable web URL of the created, updated or queried record"),
+        )
+        .arg(
+            Arg::new("content_type")
+                .long("content-type")
+                .value_name("MIME")
+                .help("Content type of field@base64:<data> attachment uploads"),
+        )
+        .arg(
+            Arg::new("filename")
+                .long("filename")
+                .value_name("NAME")
+                .default_value("attachment")
+                .help("File name given to field@base64:<data> attachment uploads"),
+        )
+        .arg(
+            Arg::new("options")
+                .long("options")
+                .value_name("FIELD")
+                .help("Output the choices of a single or multiple select field"),
+        )
+        .arg(
+            Arg::new("json")
+                .short('j')
+                .long("json")
+                .action(ArgAction::SetTrue)
+                .help("Output JSON where supported (same as --format json)"),
+        )
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .value_parser(clap::builder::PossibleValuesParser::new(output::names()))
+                .default_value("auto")
+                .help("Output format for records and listings; auto picks text on a terminal and JSON when piped"),
+        )
+        .arg(
+            Arg::new("explain")
+                .long("explain")
+                .action(ArgAction::SetTrue)
+                .help("Print the requests the operation would make and exit without calling the API"),
+        )
+        .arg(
+            Arg::new("dedup_on")
+                .long("dedup-on")
+                .value_name("FIELD")
+                .num_args(0..=1)
+                .default_missing_value("")
+                .help("Collapse listed records that share the same value in FIELD, or with create and import skip rows whose FIELD value already exists (default: the table's key_field)"),
+        )
+        .arg(
+            Arg::new("dedup_keep")
+                .long("dedup-keep")
+                .value_parser(["first", "newest"])
+                .default_value("first")
+                .help("Which duplicate to keep with --dedup-on"),
+        )
+        .arg(
+            Arg::new("upsert_on")
+                .long("upsert-on")
+                .value_name("FIELD")
+                .num_args(0..=1)
+                .default_missing_value("")
+                .help("create, import: match existing records on FIELD and update them instead (default: the table's key_field)"),
+        )
+        .arg(
+            Arg::new("on_conflict")
+                .long("on-conflict")
+                .value_parser(["skip", "overwrite", "error"])
+                .help("create, import: what to do when a record with the same key already exists (default with --upsert-on: overwrite)"),
+        )
+        .arg(
+            Arg::new("verbose")
+                .short('v')
+                .long("verbose")
+                .action(ArgAction::SetTrue)
+                .help("Report each response and the rate-limit status on stderr"),
+        )
+        .arg(
+            Arg::new("strict_response")
+                .long("strict-response")
+                .action(ArgAction::SetTrue)
+                .help("Treat empty or minimal successful responses as errors"),
+        )
+        .arg(
+            Arg::new("header")
+                .long("header")
+                .value_name("NAME: VALUE")
+                .action(ArgAction::Append)
+                .help("Add a header to every request (repeatable); Authorization cannot be overridden"),
+        )
+        .arg(
+            Arg::new("quiet_success")
+                .long("quiet-success")
+                .action(ArgAction::SetTrue)
+                .help("Print nothing when writes succeed; errors are still reported"),
+        )
+        .arg(
+            Arg::new("progress_json")
+                .long("progress-json")
+                .action(ArgAction::SetTrue)
+                .help("Report progress of listings, batches and imports as JSON lines on stderr"),
+        )
+        .arg(
+            Arg::new("limit_requests")
+                .long("limit-requests")
+                .value_name("N")
+                .value_parser(clap::value_parser!(usize))
+                .help("Abort if the invocation would make more than N HTTP requests"),
+        )
+        .arg(
+            Arg::new("base_delay")
+                .long("base-delay")
+                .value_name("MS")
+                .value_parser(clap::value_parser!(u64))
+                .help("Wait MS milliseconds before every request, including retries and further pages"),
+        )
+        .arg(
+            Arg::new("max_retries")
+                .long("max-retries")
+                .value_name("N")
+                .value_parser(clap::value_parser!(u32).range(0..=10))
+                .default_value("3")
+                .help("Retry rate-limited requests, and server errors and connection failures of reads, up to N times"),
+        )
+        .arg(
+            Arg::new("retry_base_ms")
+                .long("retry-base-ms")
+                .value_name("MS")
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .default_value("500")
+                .help("Delay before the first retry, doubled for each further retry"),
+        )
+        .arg(
+            Arg::new("retry_max_ms")
+                .long("retry-max-ms")
+                .value_name("MS")
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .default_value("30000")
+                .help("Longest delay between retries"),
+        )
+        .arg(
+            Arg::new("retry_jitter")
+                .long("retry-jitter")
+                .value_parser(["none", "full"])
+                .default_value("full")
+                .help("Randomize each retry delay between zero and the backoff (full) or use it exactly (none)"),
+        )
+        .arg(
+            Arg::new("assume_schema")
+                .long("assume-schema")
+                .value_name("FILE")
+                .help("Use the table schema in FILE instead of fetching it"),
+        )
+        .arg(
+            Arg::new("startup_retries")
+                .long("startup-retries")
+                .value_name("N")
+                .value_parser(clap::value_parser!(u32).range(0..=20))
+                .default_value("0")
+                .help("Retry the initial schema refresh up to N times on connection failures, e.g. while the network comes up"),
+        )
+        .arg(
+            Arg::new("audit_log")
+                .long("audit-log")
+                .value_name("FILE")
+                .help("Append a JSON line to FILE for every record created or updated (overrides audit_log in the config)"),
+        )
+        .arg(
+            Arg::new("curl")
+                .long("curl")
+                .action(ArgAction::SetTrue)
+                .help("Print each request as an equivalent curl command on stderr, with the API key redacted"),
+        )
+        .arg(
+            Arg::new("max_body_size")
+                .long("max-body-size")
+                .value_name("BYTES")
+                .value_parser(clap::value_parser!(usize))
+                .help("Abort instead of reading a response body larger than BYTES"),
+        )
+        .arg(
+            Arg::new("rename_map")
+                .long("rename-map")
+                .value_name("FILE")
+                .help("JSON object mapping field names to the column names used in exports"),
+        )
+        .arg(
+            Arg::new("ignore_unknown")
+                .long("ignore-unknown")
+                .action(ArgAction::SetTrue)
+                .help("Allow --rename-map to name fields that are not in the schema"),
+        )
+        .arg(
+            Arg::new("all")
+                .short('a')
+                .long("all")
+                .action(ArgAction::SetTrue)
+                .help("List every record instead of the first 100, following pagination"),
+        )
+        .arg(
+            Arg::new("ids_only")
+                .long("ids-only")
+                .action(ArgAction::SetTrue)
+                .help("Print only the IDs of listed records, one per line"),
+        )
+        .arg(
+            Arg::new("filter")
+                .long("filter")
+                .value_name("FORMULA")
+                .help("Only list records matching an Airtable filterByFormula formula, or @name for a formula from the config's filters"),
+        )
+        .arg(
+            Arg::new("view")
+                .long("view")
+                .value_name("NAME")
+                .help("List records from a view (name or ID), with its filters and sort order"),
+        )
+        .arg(
+            Arg::new("no_default_filter")
+                .long("no-default-filter")
+                .action(ArgAction::SetTrue)
+                .help("Ignore the table's default_filter"),
+        )
+        .arg(
+            Arg::new("sort")
+                .long("sort")
+                .value_name("FIELD[:desc]")
+                .action(ArgAction::Append)
+                .help("Sort listed records by FIELD, ascending unless :desc is given (repeatable; replaces the table's default_sort)"),
+        )
+        .arg(
+            Arg::new("filter_file")
+                .long("filter-file")
+                .value_name("PATH")
+                .conflicts_with("filter")
+                .help("Read the --filter formula from a file"),
+        )
+        .arg(
+            Arg::new("expand_links")
+                .long("expand-links")
+                .value_name("FIELD")
+                .action(ArgAction::Append)
+                .help("export-csv: write linked record FIELD as the linked records' primary field values"),
+        )
+        .arg(
+            Arg::new("require")
+                .long("require")
+                .value_name("FIELD")
+                .action(ArgAction::Append)
+                .help("validate-records: FIELD must have a value; json-schema: mark FIELD as required"),
+        )
+        .arg(
+            Arg::new("non_empty")
+                .long("non-empty")
+                .value_name("FIELD")
+                .action(ArgAction::Append)
+                .help("validate-records: FIELD must not be empty or blank"),
+        )
+        .arg(
+            Arg::new("matches")
+                .long("matches")
+                .value_name("FIELD=REGEX")
+                .action(ArgAction::Append)
+                .help("validate-records: FIELD must match REGEX"),
+        )
+        .arg(
+            Arg::new("compare_to")
+                .long("compare-to")
+                .value_name("RECORD_ID")
+                .help("Diff the queried record against another record"),
+        )
+        .arg(
+            Arg::new("show_same")
+                .long("show-same")
+                .action(ArgAction::SetTrue)
+                .help("diff-records, --compare-to: also print the fields both records share"),
+        )
+        .arg(
+            Arg::new("shell_vars")
+                .long("shell-vars")
+                .action(ArgAction::SetTrue)
+                .help("Print a queried record as RAU_<field>='value' lines for eval"),
+        )
+        .arg(
+            Arg::new("no_computed")
+                .long("no-computed")
+                .action(ArgAction::SetTrue)
+                .help("Leave computed fields (formulas, rollups, lookups...) out of a queried record"),
+        )
+        .arg(
+            Arg::new("oneline")
+                .long("oneline")
+                .action(ArgAction::SetTrue)
+                .help("Print a queried record's fields on one line as field=value; field2=value2"),
+        )
+        .arg(
+            Arg::new("oneline_width")
+                .long("oneline-width")
+                .value_name("N")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("40")
+                .help("Maximum characters shown per value with --oneline"),
+        )
+        .arg(
+            Arg::new("group_by")
+                .long("group-by")
+                .value_name("FIELD")
+                .help("Group listed records by their value in FIELD, with a count per group"),
+        )
+        .arg(
+            Arg::new("field_separator")
+                .long("field-separator")
+                .value_name("SEP")
+                .default_value(": ")
+                .help("Separator between a field name and its value in text output (\\t, \\n and \\0 are understood)"),
+        )
+        .arg(
+            Arg::new("record_separator")
+                .long("record-separator")
+                .value_name("SEP")
+                .default_value("\\n")
+                .help("Separator after each record in text listings (\\t, \\n and \\0 are understood)"),
+        )
+        .arg(
+            Arg::new("verify")
+                .long("verify")
+                .action(ArgAction::SetTrue)
+                .help("Re-read the record after an update and fail if a written field did not persist"),
+        )
+        .arg(
+            Arg::new("to")
+                .long("to")
+                .value_name("CONFIG")
+                .help("copy: create the copy in another configured table"),
+        )
+        .arg(
+            Arg::new("markdown")
+                .long("markdown")
+                .action(ArgAction::SetTrue)
+                .help("Output the schema as a Markdown table of names, types and descriptions"),
+        )
+        .arg(
+            Arg::new("max_field_length")
+                .long("max-field-length")
+                .value_name("N")
+                .value_parser(clap::value_parser!(usize))
+                .help("Truncate text values longer than N characters in human-readable output"),
+        )
+        .arg(
+            Arg::new("parallel_scan")
+                .long("parallel-scan")
+                .action(ArgAction::SetTrue)
+                .requires("all")
+                .help("Experimental: with --all, fetch record-ID partitions concurrently"),
+        )
+        .arg(
+            Arg::new("sample")
+                .long("sample")
+                .value_name("N")
+                .value_parser(clap::value_parser!(usize))
+                .conflicts_with("parallel_scan")
+                .help("List a random sample of N records from the listing (use --all to sample the whole table)"),
+        )
+        .arg(
+            Arg::new("seed")
+                .long("seed")
+                .value_name("SEED")
+                .value_parser(clap::value_parser!(u64))
+                .requires("sample")
+                .help("Seed --sample for a reproducible sample"),
+        )
+        .arg(
+            Arg::new("max_concurrency")
+                .long("max-concurrency")
+                .value_name("N")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("4")
+                .help("Maximum number of requests in flight at once"),
+        )
+        .arg(
+            Arg::new("method")
+                .long("method")
+                .value_name("METHOD")
+                .default_value("GET")
+                .help("raw-request: HTTP method to send"),
+        )
+        .arg(
+            Arg::new("path")
+                .long("path")
+                .value_name("PATH")
+                .help("raw-request: path on the Airtable API host, e.g. /v0/meta/whoami"),
+        )
+        .arg(
+            Arg::new("body")
+                .long("body")
+                .value_name("JSON|@FILE")
+                .help("raw-request: JSON request body, or @file to read it from a file"),
+        )
+}
+
+// Commands that can take the place of a record ID, e.g. `rau <config> export-sqlite out.db`
+const COMMANDS: &[&str] = &["schema", "export-sqlite", "validate-records", "copy", "get", "json-schema", "create", "set", "import", "codegen", "diff-records", "list-views", "rename-field", "download-attachments", "export-csv"];
+
+// Options resolved from the command line that apply to each table operation
+struct Options<'a> {
+    command: Option<&'a str>,
+    record_id: Option<&'a String>,
+    fields: Vec<&'a str>,
+    output_schema: bool,
+    output_fields: bool,
+    output_recent: bool,
+    primary_only: bool,
+    record_url: bool,
+    content_type: Option<&'a String>,
+    filename: &'a str,
+    options_field: Option<&'a String>,
+    // Resolved from --json and --format: text on a terminal and JSON in pipelines unless overridden
+    format: &'static dyn OutputFormat,
+    pretty: bool,
+    explain: bool,
+    dedup_on: Option<&'a String>,
+    dedup_keep: &'a str,
+    upsert_on: Option<&'a String>,
+    on_conflict: Option<&'a String>,
+    rename_map: Option<&'a String>,
+    ignore_unknown: bool,
+    all: bool,
+    ids_only: bool,
+    yes: bool,
+    dry_run: bool,
+    stdin: bool,
+    only_changed: bool,
+    // JSON object of field values the record must hold for an update to go ahead
+    if_match: Option<&'a String>,
+    // download-attachments: --field and --dir
+    attachment_field: Option<&'a String>,
+    dir: Option<&'a String>,
+    // Plain assignments to multi-value fields merge into the current values (--merge-arrays)
+    merge_arrays: bool,
+    // Resolved from --filter or --filter-file by `resolve_filter`
+    filter: Option<String>,
+    filter_file: Option<&'a String>,
+    no_default_filter: bool,
+    view: Option<&'a String>,
+    // FIELD or FIELD:desc, in priority order
+    sort: Vec<String>,
+    require: Vec<&'a str>,
+    // export-csv: linked record fields written as primary field values
+    expand_links: Vec<&'a str>,
+    non_empty: Vec<&'a str>,
+    matches: Vec<&'a str>,
+    oneline: bool,
+    no_computed: bool,
+    shell_vars: bool,
+    startup_retries: u32,
+    assume_schema: Option<&'a String>,
+    compare_to: Option<&'a String>,
+    show_same: bool,
+    oneline_width: usize,
+    group_by: Option<&'a String>,
+    field_separator: String,
+    record_separator: String,
+    verify: bool,
+    copy_to: Option<&'a String>,
+    markdown: bool,
+    max_field_length: Option<usize>,
+    parallel_scan: bool,
+    sample: Option<usize>,
+    seed: Option<u64>,
+    max_concurrency: usize,
+    // Only read to describe them in --explain; the client applies them
+    retry: RetryPolicy,
+    base_delay: Option<u64>,
+    request_limit: Option<usize>,
+}
+
+impl<'a> Options<'a> {
+    fn from_matches(matches: &'a clap::ArgMatches) -> Result<Self, Box<dyn std::error::Error>> {
+        let record_id = matches.get_one::<String>("record_id");
+        let command = record_id.map(String::as_str).filter(|id| COMMANDS.contains(id));
+        let format = output::select(matches.get_one::<String>("format").expect("format has a default"), matches.get_flag("json"))?;
+        Ok(Options {
+            command,
+            record_id: record_id.filter(|_| command.is_none()),
+            fields: matches.get_many::<String>("fields").unwrap_or_default().map(|s| s.as_str()).collect(),
+            // `rau <config> schema` is an alias for `rau <config> --schema`
+            output_schema: matches.get_flag("schema") || command == Some("schema"),
+            output_fields: matches.get_flag("fields_flag"),
+            output_recent: matches.get_flag("recent") || matches.get_flag("primary_only"),
+            primary_only: matches.get_flag("primary_only"),
+            record_url: matches.get_flag("record_url"),
+            content_type: matches.get_one::<String>("content_type"),
+            filename: matches.get_one::<String>("filename").expect("filename has a default"),
+            options_field: matches.get_one::<String>("options"),
+            format,
+            pretty: output::stdout_is_terminal(),
+            explain: matches.get_flag("explain"),
+            dedup_on: matches.get_one::<String>("dedup_on"),
+            dedup_keep: matches.get_one::<String>("dedup_keep").expect("dedup_keep has a default"),
+            upsert_on: matches.get_one::<String>("upsert_on"),
+            on_conflict: matches.get_one::<String>("on_conflict"),
+            rename_map: matches.get_one::<String>("rename_map"),
+            ignore_unknown: matches.get_flag("ignore_unknown"),
+            all: matches.get_flag("all"),
+            ids_only: matches.get_flag("ids_only"),
+            yes: matches.get_flag("yes"),
+            dry_run: matches.get_flag("dry_run"),
+            stdin: matches.get_flag("stdin"),
+            only_changed: matches.get_flag("only_changed"),
+            merge_arrays: matches.get_flag("merge_arrays") && !matches.get_flag("replace_arrays"),
+            if_match: matches.get_one::<String>("if_match"),
+            attachment_field: matches.get_one::<String>("field"),
+            dir: matches.get_one::<String>("dir"),
+            filter: matches.get_one::<String>("filter").cloned(),
+            filter_file: matches.get_one::<String>("filter_file"),
+            no_default_filter: matches.get_flag("no_default_filter"),
+            view: matches.get_one::<String>("view"),
+            sort: matches.get_many::<String>("sort").unwrap_or_default().cloned().collect(),
+            require: matches.get_many::<String>("require").unwrap_or_default().map(|s| s.as_str()).collect(),
+            expand_links: matches.get_many::<String>("expand_links").unwrap_or_default().map(|s| s.as_str()).collect(),
+            non_empty: matches.get_many::<String>("non_empty").unwrap_or_default().map(|s| s.as_str()).collect(),
+            matches: matches.get_many::<String>("matches").unwrap_or_default().map(|s| s.as_str()).collect(),
+            oneline: matches.get_flag("oneline"),
+            no_computed: matches.get_flag("no_computed"),
+            shell_vars: matches.get_flag("shell_vars"),
+            assume_schema: matches.get_one::<String>("assume_schema"),
+            startup_retries: *matches.get_one::<u32>("startup_retries").expect("startup_retries has a default"),
+            compare_to: matches.get_one::<String>("compare_to"),
+            show_same: matches.get_flag("show_same"),
+            oneline_width: *matches.get_one::<usize>("oneline_width").expect("oneline_width has a default"),
+            group_by: matches.get_one::<String>("group_by"),
+            field_separator: unescape_separator(matches.get_one::<String>("field_separator").expect("field_separator has a default")),
+            record_separator: unescape_separator(matches.get_one::<String>("record_separator").expect("record_separator has a default")),
+            verify: matches.get_flag("verify"),
+            copy_to: matches.get_one::<String>("to"),
+            markdown: matches.get_flag("markdown"),
+            max_field_length: matches.get_one::<usize>("max_field_length").copied(),
+            parallel_scan: matches.get_flag("parallel_scan"),
+            sample: matches.get_one::<usize>("sample").copied(),
+            seed: matches.get_one::<u64>("seed").copied(),
+            max_concurrency: *matches.get_one::<usize>("max_concurrency").expect("max_concurrency has a default"),
+            retry: RetryPolicy::from_matches(matches)?,
+            base_delay: matches.get_one::<u64>("base_delay").copied(),
+            request_limit: matches.get_one::<usize>("limit_requests").copied(),
+        })
+    }
+
+    // Use the table's default_filter and default_sort where --filter and --sort were not given.
+    // `set` never picks up a default filter, since it decides which records are written.
+    fn apply_table_defaults(&mut self, table_config: &TableConfig) {
+        if self.filter.is_none() && self.filter_file.is_none() && !self.no_default_filter && self.command != Some("set") {
+            self.filter = table_config.default_filter.clone();
+        }
+        if self.sort.is_empty() {
+            self.sort = table_config.default_sort.clone();
+        }
+    }
+
+    // Read --filter-file and expand `@name` references to the config's named filters
+    fn resolve_filter(&mut self, filters: &HashMap<String, String>) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(path) = self.filter_file {
+            let formula = fs::read_to_string(path).map_err(|err| format!("Failed to read filter file {}: {}", path, err))?;
+            self.filter = Some(formula.trim().to_string());
+        }
+        if let Some(name) = self.filter.as_deref().and_then(|filter| filter.strip_prefix('@')) {
+            let formula = filters.get(name).ok_or_else(|| {
+                let mut names: Vec<&str> = filters.keys().map(String::as_str).collect();
+                names.sort();
+                format!("Unknown filter '@{}'. Named filters in the config: {}", name, names.join(", "))
+            })?;
+            self.filter = Some(formula.trim().to_string());
+        }
+        Ok(())
+    }
+
+    // Whether the operation creates or updates records rather than only reading
+    fn is_write(&self) -> bool {
+        if let Some(command) = self.command {
+            return matches!(command, "copy" | "create" | "set" | "import" | "rename-field");
+        }
+        if self.output_schema || self.output_fields || self.output_recent {
+            return false;
+        }
+        match self.record_id {
+            Some(_) => self.fields.iter().any(|field| is_update_arg(field)),
+            None => true,
+        }
+    }
+}
+
+// Query parameters for listing records: the first 100 unless --all, narrowed by --filter
+fn list_params(opts: &Options) -> Vec<(String, String)> {
+    let mut params = Vec::new();
+    if !opts.all {
+        params.push(("maxRecords".to_string(), "100".to_string()));
+    }
+    if let Some(filter) = &opts.filter {
+        params.push(("filterByFormula".to_string(), filter.clone()));
+    }
+    if let Some(view) = opts.view {
+        params.push(("view".to_string(), view.clone()));
+    }
+    for (index, spec) in opts.sort.iter().enumerate() {
+        let (field, direction) = match spec.rsplit_once(':') {
+            Some((field, direction)) if direction == "asc" || direction == "desc" => (field, direction),
+            _ => (spec.as_str(), "asc"),
+        };
+        params.push((format!("sort[{}][field]", index), field.to_string()));
+        params.push((format!("sort[{}][direction]", index), direction.to_string()));
+    }
+    params
+}
+
+// Fetch the records selected by --all and --filter, stopping early on Ctrl-C
+async fn list_records(client: &AirtableClient, table_config: &TableConfig, opts: &Options<'_>) -> Result<Vec<Record>, Box<dyn std::error::Error>> {
+    let records_url = format!("{}/v0/{}/{}", client.api_url, table_config.base_id, table_config.table_name);
+    if opts.all && opts.parallel_scan {
+        return parallel_scan(client, &records_url, opts).await;
+    }
+    let mut pager = RecordPager::new(client, records_url, list_params(opts));
+    let mut records = Vec::new();
+    while let Some(page) = pager.next_page().await? {
+        records.extend(page);
+        if interrupted() {
+            eprintln!("Interrupted after fetching {} records", records.len());
+            break;
+        }
+    }
+    Ok(records)
+}
+
+// Pick `size` records uniformly at random from the listing with reservoir sampling, so only the
+// sample is held in memory however many pages are read. Sampled records keep their listing order.
+async fn sample_records(client: &AirtableClient, table_config: &TableConfig, opts: &Options<'_>, size: usize) -> Result<Vec<Record>, Box<dyn std::error::Error>> {
+    let records_url = format!("{}/v0/{}/{}", client.api_url, table_config.base_id, table_config.table_name);
+    let mut rng = match opts.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    let mut pager = RecordPager::new(client, records_url, list_params(opts));
+    let mut reservoir: Vec<(usize, Record)> = Vec::with_capacity(size);
+    let mut seen = 0;
+    while let Some(page) = pager.next_page().await? {
+        for record in page {
+            if reservoir.len() < size {
+                reservoir.push((seen, record));
+            } else {
+                let slot = rng.gen_range(0..=seen);
+                if slot < size {
+                    reservoir[slot] = (seen, record);
+                }
+            }
+            seen += 1;
+        }
+        if interrupted() {
+            eprintln!("Interrupted after reading {} records; sampling from those", seen);
+            break;
+        }
+    }
+    reservoir.sort_by_key(|(position, _)| *position);
+    Ok(reservoir.into_iter().map(|(_, record)| record).collect())
+}
+
+// Characters that follow "rec" in record IDs, used to split a table into partitions
+const RECORD_ID_CHARS: &str = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+// Experimental: list every record by fetching one partition per record-ID character concurrently.
+// Records created or deleted during the scan may be missed, and any record seen twice is kept once.
+async fn parallel_scan(client: &AirtableClient, records_url: &str, opts: &Options<'_>) -> Result<Vec<Record>, Box<dyn std::error::Error>> {
+    let partitions = RECORD_ID_CHARS.chars().map(|c| {
+        let partition = format!("FIND('{}', MID(RECORD_ID(), 4, 1))", c);
+        let formula = match &opts.filter {
+            Some(filter) => format!("AND({}, {})", filter, partition),
+            None => partition,
+        };
+        let mut params: Vec<(String, String)> = list_params(opts).into_iter().filter(|(name, _)| name != "filterByFormula").collect();
+        params.push(("filterByFormula".to_string(), formula));
+        async move {
+            let mut pager = RecordPager::new(client, records_url.to_string(), params);
+            let mut records = Vec::new();
+            while let Some(page) = pager.next_page().await? {
+                records.extend(page);
+                if interrupted() {
+                    break;
+                }
+            }
+            Ok::<_, Box<dyn std::error::Error>>(records)
+        }
+    });
+
+    let partitions: Vec<Vec<Record>> = stream::iter(partitions).buffer_unordered(opts.max_concurrency.max(1)).try_collect().await?;
+    let mut seen = std::collections::HashSet::new();
+    Ok(partitions.into_iter().flatten().filter(|record| seen.insert(record.id.clone())).collect())
+}
+
+// Record IDs fetched per listRecords formula by `get`
+const IDS_PER_REQUEST: usize = 50;
+
+// Fetch many records by ID with one list request per chunk instead of one GET per record
+async fn get_records(client: &AirtableClient, table_config: &TableConfig, ids: &[String]) -> Result<Vec<Record>, Box<dyn std::error::Error>> {
+    let records_url = format!("{}/v0/{}/{}", client.api_url, table_config.base_id, table_config.table_name);
+    let mut found: HashMap<String, Record> = HashMap::new();
+    for chunk in ids.chunks(IDS_PER_REQUEST) {
+        if interrupted() {
+            eprintln!("Interrupted after fetching {} records", found.len());
+            break;
+        }
+        let clauses: Vec<String> = chunk.iter().map(|id| format!("RECORD_ID()={}", formula_string(id))).collect();
+        let params = vec![("filterByFormula".to_string(), format!("OR({})", clauses.join(", ")))];
+        let mut pager = RecordPager::new(client, records_url.clone(), params);
+        while let Some(page) = pager.next_page().await? {
+            found.extend(page.into_iter().map(|record| (record.id.clone(), record)));
+        }
+    }
+
+    // Keep the order the IDs were given in
+    let mut records = Vec::new();
+    for id in ids {
+        match found.remove(id) {
+            Some(record) => records.push(record),
+            None if !interrupted() => eprintln!("Not found: {}", id),
+            None => {}
+        }
+    }
+    Ok(records)
+}
+
+// Airtable accepts at most 10 records per create or update request
+const WRITE_BATCH_SIZE: usize = 10;
+
+// How `create` treats rows whose key field matches an existing record
+struct ConflictPolicy<'a> {
+    key: &'a str,
+    // skip, overwrite or error
+    mode: &'a str,
+    // Leave existing records alone when overwriting would not change them (--only-changed)
+    only_changed: bool,
+    // Multi-value fields merged into an existing record's values when overwriting (--merge-arrays)
+    merged_fields: Vec<String>,
+}
+
+impl<'a> ConflictPolicy<'a> {
+    fn from_options(opts: &'a Options, table_config: &'a TableConfig, available_fields: &[Field]) -> Result<Option<Self>, Box<dyn std::error::Error>> {
+        // --dedup-on skips rows whose key already exists in the table or earlier in the input
+        if let Some(dedup_on) = opts.dedup_on {
+            if opts.upsert_on.is_some() || opts.on_conflict.is_some() {
+                return Err(RauError::Usage("--dedup-on cannot be combined with --upsert-on or --on-conflict".to_string()).into());
+            }
+            let key = key_field_or(dedup_on, table_config, available_fields, "--dedup-on")?;
+            return Ok(Some(ConflictPolicy { key, mode: "skip", only_changed: false, merged_fields: Vec::new() }));
+        }
+        if opts.upsert_on.is_none() && opts.on_conflict.is_none() {
+            return Ok(None);
+        }
+        let key = key_field_or(opts.upsert_on.map(String::as_str).unwrap_or_default(), table_config, available_fields, "--on-conflict")?;
+        let mode = opts.on_conflict.map(String::as_str).unwrap_or("overwrite");
+        let merged_fields = if opts.merge_arrays {
+            available_fields.iter().filter(|field| is_array_type(&field.field_type)).map(|field| field.name.clone()).collect()
+        } else {
+            Vec::new()
+        };
+        Ok(Some(ConflictPolicy { key, mode, only_changed: opts.only_changed, merged_fields }))
+    }
+}
+
+// Map each given key value (as JSON text) to the existing record holding it. Only the key field
+// is fetched unless `all_fields` is set.
+async fn fetch_existing_keys(client: &AirtableClient, table_config: &TableConfig, key: &str, values: &[&Value], all_fields: bool) -> Result<HashMap<String, Record>, Box<dyn std::error::Error>> {
+    let records_url = format!("{}/v0/{}/{}", client.api_url, table_config.base_id, table_config.table_name);
+    let mut existing = HashMap::new();
+    for chunk in values.chunks(IDS_PER_REQUEST) {
+        let clauses: Vec<String> = chunk.iter().map(|value| key_clause(key, value)).collect();
+        let mut params = vec![("filterByFormula".to_string(), format!("OR({})", clauses.join(", ")))];
+        if !all_fields {
+            params.push(("fields[]".to_string(), key.to_string()));
+        }
+        let mut pager = RecordPager::new(client, records_url.clone(), params);
+        while let Some(page) = pager.next_page().await? {
+            for record in page {
+                if let Some(value) = record.fields.get(key) {
+                    existing.insert(key_text(value), record);
+                }
+            }
+        }
+    }
+    Ok(existing)
+}
+
+// Create numbered rows in batches, first resolving rows whose key already exists according to `policy`.
+// Every row's action is decided before anything is written, so `error` never leaves a partial write
+// of these rows. Returns the number of rows skipped.
+async fn create_records(client: &AirtableClient, table_config: &TableConfig, rows: Vec<(usize, Map<String, Value>)>, policy: Option<&ConflictPolicy<'_>>) -> Result<usize, Box<dyn std::error::Error>> {
+    let records_url = format!("{}/v0/{}/{}", client.api_url, table_config.base_id, table_config.table_name);
+    let existing = match policy {
+        Some(policy) => {
+            let values: Vec<&Value> = rows.iter().filter_map(|(_, row)| row.get(policy.key)).filter(|value| !value.is_null()).collect();
+            let all_fields = policy.only_changed || !policy.merged_fields.is_empty();
+            fetch_existing_keys(client, table_config, policy.key, &values, all_fields).await?
+        }
+        None => HashMap::new(),
+    };
+
+    let mut creates: Vec<(usize, Map<String, Value>)> = Vec::new();
+    let mut updates: Vec<(usize, String, Map<String, Value>)> = Vec::new();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    let mut unchanged = 0;
+    let mut skipped = 0;
+    for (number, mut row) in rows {
+        let key = policy.and_then(|policy| row.get(policy.key)).filter(|value| !value.is_null()).map(key_text);
+        let (Some(policy), Some(key)) = (policy, key) else {
+            creates.push((number, row));
+            continue;
+        };
+        if let Some(record) = existing.get(&key) {
+            let id = &record.id;
+            if policy.mode == "overwrite" {
+                merge_into_current(&mut row, &policy.merged_fields, record);
+            }
+            if policy.only_changed && policy.mode == "overwrite" {
+                row.retain(|name, value| !same_field_value(value, record.fields.get(name)));
+            }
+            match policy.mode {
+                "error" => return Err(format!("Row {}: a record with {} = {} already exists ({})", number, policy.key, key, id).into()),
+                "skip" if client.quiet_success => skipped += 1,
+                "skip" => {
+                    skipped += 1;
+                    eprint!("Row {} skipped, {} = {} exists: ", number, policy.key, key);
+                    println!("{}", id);
+                }
+                _ if row.is_empty() => {
+                    unchanged += 1;
+                    if !client.quiet_success {
+                        eprint!("Row {} unchanged: ", number);
+                        println!("{}", id);
+                    }
+                }
+                _ => updates.push((number, id.clone(), row)),
+            }
+        } else if let Some(&earlier) = seen.get(&key) {
+            // The same key appears twice in the input; treat the earlier row as the existing record
+            match policy.mode {
+                "error" => return Err(format!("Row {}: {} = {} repeats row {}", number, policy.key, key, earlier).into()),
+                "skip" if client.quiet_success => skipped += 1,
+                "skip" => {
+                    skipped += 1;
+                    eprintln!("Row {} skipped, {} = {} repeats row {}", number, policy.key, key, earlier);
+                }
+                _ => {
+                    if let Some((_, fields)) = creates.iter_mut().find(|(row_number, _)| *row_number == earlier) {
+                        fields.extend(row);
+                    }
+                    if !client.quiet_success {
+                        eprintln!("Row {} merged into row {}", number, earlier);
+                    }
+                }
+            }
+        } else {
+            seen.insert(key, number);
+            creates.push((number, row));
+        }
+    }
+
+    let mut batch_index = 0;
+    for batch in creates.chunks(WRITE_BATCH_SIZE) {
+        batch_index += 1;
+        if interrupted() {
+            return Err(RauError::Interrupted(format!("Interrupted before creating row {}", batch[0].0)).into());
+        }
+        let records: Vec<Value> = batch.iter().map(|(_, fields)| json!({ "fields": fields })).collect();
+        let (status, text) = client.send(client.post(&records_url).json(&json!({ "records": records }))).await?;
+        if !status.is_success() {
+            client.progress(json!({ "event": "batch", "index": batch_index, "records": batch.len(), "status": "failed" }));
+            return Err(RauError::from_status(status, format!("Failed to create records from row {}. Status: {}, Response: {}", batch[0].0, status, text)));
+        }
+        let created: RecordsResponse = client.parse(&text)?;
+        client.progress(json!({ "event": "batch", "index": batch_index, "records": batch.len(), "status": "ok" }));
+        for ((_, fields), record) in batch.iter().zip(&created.records) {
+            client.audit(table_config, "create", &record.id, &json!(fields));
+        }
+        if !client.quiet_success {
+            for ((number, _), record) in batch.iter().zip(&created.records) {
+                eprint!("Row {} created: ", number);
+                println!("{}", record.id);
+            }
+        }
+        client.records_affected.fetch_add(created.records.len(), Ordering::SeqCst);
+    }
+
+    for batch in updates.chunks(WRITE_BATCH_SIZE) {
+        batch_index += 1;
+        if interrupted() {
+            return Err(RauError::Interrupted(format!("Interrupted before updating row {}", batch[0].0)).into());
+        }
+        let records: Vec<Value> = batch.iter().map(|(_, id, fields)| json!({ "id": id, "fields": fields })).collect();
+        let (status, text) = client.send(client.patch(&records_url).json(&json!({ "records": records }))).await?;
+        if !status.is_success() {
+            client.progress(json!({ "event": "batch", "index": batch_index, "records": batch.len(), "status": "failed" }));
+            return Err(RauError::from_status(status, format!("Failed to update records from row {}. Status: {}, Response: {}", batch[0].0, status, text)));
+        }
+        let updated: RecordsResponse = client.parse(&text)?;
+        client.progress(json!({ "event": "batch", "index": batch_index, "records": batch.len(), "status": "ok" }));
+        for (_, id, fields) in batch {
+            client.audit(table_config, "update", id, &json!(fields));
+        }
+        if !client.quiet_success {
+            for ((number, _, _), record) in batch.iter().zip(&updated.records) {
+                eprint!("Row {} updated: ", number);
+                println!("{}", record.id);
+            }
+        }
+        client.records_affected.fetch_add(updated.records.len(), Ordering::SeqCst);
+    }
+    if unchanged > 0 && !client.quiet_success {
+        eprintln!("Skipped {} unchanged record(s)", unchanged);
+    }
+    Ok(skipped)
+}
+
+// Rows read from CSV before they are handed to `create_records`, so input is streamed rather than buffered
+const IMPORT_CHUNK_ROWS: usize = 50;
+
+// Create a record for each CSV row, from a file or from stdin with `--stdin` or `-`. The header row
+// names the fields; rows are written in chunks as they are read, and bad rows are reported by
+// row number (counting data rows from 1) and skipped.
+async fn import_csv(client: &AirtableClient, table_config: &TableConfig, available_fields: &[Field], opts: &Options<'_>, policy: Option<&ConflictPolicy<'_>>) -> Result<(), Box<dyn std::error::Error>> {
+    let input: Box<dyn io::Read> = match opts.fields.first() {
+        _ if opts.stdin => Box::new(io::stdin()),
+        Some(&"-") => Box::new(io::stdin()),
+        Some(path) => Box::new(fs::File::open(path).map_err(|err| format!("Failed to open {}: {}", path, err))?),
+        None => return Err(RauError::Usage("Usage: rau <config> import <file.csv> (or --stdin)".to_string()).into()),
+    };
+    let mut reader = csv::Reader::from_reader(input);
+
+    // Map each column to its field, so unknown columns fail before anything is written
+    let mut columns: Vec<Option<&Field>> = Vec::new();
+    for name in reader.headers()?.iter() {
+        match available_fields.iter().find(|field| field.name == name) {
+            Some(field) => columns.push(Some(field)),
+            None if opts.ignore_unknown => columns.push(None),
+            None => return Err(format!("Column '{}' is not a field of the table (use --ignore-unknown to skip it)", name).into()),
+        }
+    }
+
+    let mut chunk = Vec::new();
+    let mut chunks = 0;
+    let mut imported = 0;
+    let mut skipped = 0;
+    let mut errors = 0;
+    for (index, result) in reader.records().enumerate() {
+        let number = index + 1;
+        if interrupted() {
+            return Err(RauError::Interrupted(format!("Interrupted before row {}", number)).into());
+        }
+        let record = match result {
+            Ok(record) => record,
+            Err(err) => {
+                eprintln!("Row {}: {}", number, err);
+                errors += 1;
+                continue;
+            }
+        };
+        let mut row = Map::new();
+        for (column, cell) in columns.iter().zip(record.iter()) {
+            if let Some(field) = column {
+                if !cell.is_empty() {
+                    row.insert(field.name.clone(), csv_cell_value(&field.field_type, cell));
+                }
+            }
+        }
+        chunk.push((number, row));
+        if chunk.len() == IMPORT_CHUNK_ROWS {
+            chunks += 1;
+            imported += chunk.len();
+            skipped += import_chunk(client, table_config, std::mem::take(&mut chunk), policy, chunks).await?;
+        }
+    }
+    if !chunk.is_empty() {
+        chunks += 1;
+        imported += chunk.len();
+        skipped += import_chunk(client, table_config, chunk, policy, chunks).await?;
+    }
+    if let (Some(policy), true) = (policy, skipped > 0 && !client.quiet_success) {
+        eprintln!("Skipped {} row(s) whose {} already exists", skipped, policy.key);
+    }
+
+    if errors > 0 {
+        return Err(format!("Processed {} row(s), {} error(s)", imported, errors).into());
+    }
+    if !client.quiet_success {
+        eprintln!("Processed {} row(s), {} error(s)", imported, errors);
+    }
+    Ok(())
+}
+
+// Write one chunk of imported rows, reporting its progress before returning a failed request.
+// Returns the number of rows skipped.
+async fn import_chunk(client: &AirtableClient, table_config: &TableConfig, rows: Vec<(usize, Map<String, Value>)>, policy: Option<&ConflictPolicy<'_>>, index: usize) -> Result<usize, Box<dyn std::error::Error>> {
+    let (first, count) = (rows[0].0, rows.len());
+    let written = create_records(client, table_config, rows, policy).await;
+    let status = if written.is_ok() { "ok" } else { "failed" };
+    client.progress(json!({ "event": "chunk", "index": index, "first_row": first, "rows": count, "status": status }));
+    written
+}
+
+// A CSV cell as the JSON value Airtable expects for the field's type
+fn csv_cell_value(field_type: &str, cell: &str) -> Value {
+    match field_type {
+        "number" | "currency" | "percent" | "rating" | "duration" => cell.trim().parse::<f64>().map(|number| json!(number)).unwrap_or_else(|_| json!(cell)),
+        "checkbox" => json!(matches!(cell.trim().to_lowercase().as_str(), "true" | "yes" | "1" | "x" | "checked")),
+        "multipleSelects" | "multipleRecordLinks" => json!(cell.split(',').map(str::trim).filter(|item| !item.is_empty()).collect::<Vec<_>>()),
+        "multipleAttachments" => json!(cell.split_whitespace().map(|url| json!({ "url": url })).collect::<Vec<_>>()),
+        _ => json!(cell),
+    }
+}
+
+// Apply the same field values to every record matching --filter, in batches. With --merge-arrays,
+// multi-value fields are merged into each record's current values.
+async fn set_matching(client: &AirtableClient, table_config: &TableConfig, available_fields: &[Field], opts: &Options<'_>) -> Result<(), Box<dyn std::error::Error>> {
+    let filter = opts.filter.as_ref().ok_or("set needs --filter to select the records to update")?;
+    let mut values = Map::new();
+    for field in &opts.fields {
+        add_field_assignment(&mut values, field).map_err(RauError::Usage)?;
+    }
+    if values.is_empty() {
+        return Err(RauError::Usage("Usage: rau <config> set --filter <FORMULA> <field=value>...".to_string()).into());
+    }
+
+    let merged_fields = merged_array_fields(available_fields, &values, opts.merge_arrays);
+
+    let records_url = format!("{}/v0/{}/{}", client.api_url, table_config.base_id, table_config.table_name);
+    let mut params = vec![("filterByFormula".to_string(), filter.clone())];
+    params.extend(values.keys().map(|name| ("fields[]".to_string(), name.clone())));
+    let mut pager = RecordPager::new(client, records_url.clone(), params);
+    let mut updates: Vec<(String, Map<String, Value>)> = Vec::new();
+    let mut unchanged = 0;
+    while let Some(page) = pager.next_page().await? {
+        for record in page {
+            let mut fields = values.clone();
+            merge_into_current(&mut fields, &merged_fields, &record);
+            if opts.only_changed && fields.iter().all(|(name, value)| same_field_value(value, record.fields.get(name))) {
+                unchanged += 1;
+            } else {
+                updates.push((record.id, fields));
+            }
+        }
+    }
+    let ids: Vec<&String> = updates.iter().map(|(id, _)| id).collect();
+    if unchanged > 0 && !client.quiet_success {
+        eprintln!("Skipped {} unchanged record(s)", unchanged);
+    }
+
+    if opts.dry_run {
+        for id in &ids {
+            println!("{}", id);
+        }
+        eprintln!("Would update {} record(s)", ids.len());
+        return Ok(());
+    }
+    if !opts.yes {
+        return Err(RauError::Usage(format!("{} record(s) match. Pass --yes to update them or --dry-run to list them.", ids.len())).into());
+    }
+
+    let mut updated = 0;
+    for (index, batch) in updates.chunks(WRITE_BATCH_SIZE).enumerate() {
+        if interrupted() {
+            return Err(RauError::Interrupted(format!("Interrupted after updating {} of {} record(s)", updated, ids.len())).into());
+        }
+        let records: Vec<Value> = batch.iter().map(|(id, fields)| json!({ "id": id, "fields": fields })).collect();
+        let (status, text) = client.send(client.patch(&records_url).json(&json!({ "records": records }))).await?;
+        if !status.is_success() {
+            client.progress(json!({ "event": "batch", "index": index + 1, "records": batch.len(), "status": "failed" }));
+            return Err(RauError::from_status(status, format!("Failed to update records after updating {} of {}. Status: {}, Response: {}", updated, ids.len(), status, text)));
+        }
+        let response: RecordsResponse = client.parse(&text)?;
+        client.progress(json!({ "event": "batch", "index": index + 1, "records": batch.len(), "status": "ok" }));
+        for (id, fields) in batch {
+            client.audit(table_config, "update", id, &Value::Object(fields.clone()));
+        }
+        updated += response.records.len();
+        client.records_affected.fetch_add(response.records.len(), Ordering::SeqCst);
+    }
+    if !client.quiet_success {
+        println!("Updated {} of {} matching record(s)", updated, ids.len());
+    }
+    Ok(())
+}
+
+// A key value as text, so a key matches whether it arrives as a number or as text, and 42.0
+// parsed from CSV matches the 42 Airtable returns
+fn key_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Number(number) => match number.as_f64() {
+            Some(float) if float.fract() == 0.0 && float.abs() < 1e15 => format!("{}", float as i64),
+            Some(float) => float.to_string(),
+            None => number.to_string(),
+        },
+        other => other.to_string(),
+    }
+}
+
+// Formula matching records whose `key` field holds `value`. Text and numbers are compared as
+// text on both sides, the same way `key_text` normalizes them.
+fn key_clause(key: &str, value: &Value) -> String {
+    match value {
+        Value::String(_) | Value::Number(_) => format!("{}&''={}", formula_field(key), formula_string(&key_text(value))),
+        other => format!("{}={}", formula_field(key), formula_literal(other)),
+    }
+}
+
+// Reference a field by name in a formula, escaping braces and backslashes in the name
+fn formula_field(name: &str) -> String {
+    format!("{{{}}}", name.replace('\\', "\\\\").replace('}', "\\}"))
+}
+
+// A JSON value written as an Airtable formula literal
+fn formula_literal(value: &Value) -> String {
+    match value {
+        Value::String(text) => formula_string(text),
+        Value::Number(number) => number.to_string(),
+        Value::Bool(true) => "TRUE()".to_string(),
+        Value::Bool(false) => "FALSE()".to_string(),
+        other => formula_string(&other.to_string()),
+    }
+}
+
+// Quote text as a string literal in an Airtable formula
+fn formula_string(text: &str) -> String {
+    format!("'{}'", text.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+// Per-field checks applied by `validate-records`
+struct ValidationRules<'a> {
+    require: Vec<&'a str>,
+    non_empty: Vec<&'a str>,
+    matches: Vec<(&'a str, Regex)>,
+}
+
+impl<'a> ValidationRules<'a> {
+    fn from_options(opts: &Options<'a>) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut matches = Vec::new();
+        for rule in &opts.matches {
+            let (field, pattern) = rule
+                .split_once('=')
+                .ok_or_else(|| format!("Invalid --matches rule '{}', expected Field=<regex>", rule))?;
+            matches.push((field, Regex::new(pattern)?));
+        }
+        if opts.require.is_empty() && opts.non_empty.is_empty() && matches.is_empty() {
+            return Err("validate-records needs at least one --require, --non-empty or --matches rule".into());
+        }
+        Ok(ValidationRules {
+            require: opts.require.clone(),
+            non_empty: opts.non_empty.clone(),
+            matches,
+        })
+    }
+
+    // Describe every rule the record fails
+    fn check(&self, record: &Record) -> Vec<String> {
+        let mut failures = Vec::new();
+        for field in &self.require {
+            if matches!(record.fields.get(*field), None | Some(Value::Null)) {
+                failures.push(format!("--require {}", field));
+            }
+        }
+        for field in &self.non_empty {
+            let empty = match record.fields.get(*field) {
+                None | Some(Value::Null) => true,
+                Some(Value::String(text)) => text.trim().is_empty(),
+                Some(Value::Array(items)) => items.is_empty(),
+                Some(Value::Object(map)) => map.is_empty(),
+                Some(_) => false,
+            };
+            if empty {
+                failures.push(format!("--non-empty {}", field));
+            }
+        }
+        for (field, regex) in &self.matches {
+            let matched = match record.fields.get(*field) {
+                None | Some(Value::Null) => false,
+                Some(Value::String(text)) => regex.is_match(text),
+                Some(other) => regex.is_match(&other.to_string()),
+            };
+            if !matched {
+                failures.push(format!("--matches {}={}", field, regex));
+            }
+        }
+        failures
+    }
+}
+
+// Look up a queried field, which may be a path into its JSON like `Config.timeout` or `Tags[0]`.
+// An exact field name always wins, and text holding JSON is parsed on the way down.
+fn field_path_value(fields: &Value, path: &str) -> Option<Value> {
+    if let Some(value) = fields.get(path) {
+        return Some(value.clone());
+    }
+    let split = path.find(['.', '['])?;
+    let mut value = fields.get(&path[..split])?.clone();
+    let mut rest = &path[split..];
+    while !rest.is_empty() {
+        if let Value::String(text) = &value {
+            value = serde_json::from_str(text).ok()?;
+        }
+        if let Some(after) = rest.strip_prefix('[') {
+            let (index, remainder) = after.split_once(']')?;
+            value = value.get(index.trim().parse::<usize>().ok()?)?.clone();
+            rest = remainder;
+        } else {
+            let after = rest.strip_prefix('.')?;
+            let end = after.find(['.', '[']).unwrap_or(after.len());
+            value = value.get(&after[..end])?.clone();
+            rest = &after[end..];
+        }
+    }
+    Some(value)
+}
+
+// Print `RAU_<field>='value'` lines for `eval`. Characters outside A-Z, a-z, 0-9 and _ become _,
+// and a number is appended to a name already taken. The record ID is RAU_ID.
+fn print_shell_vars(record: &Record, fields: &[&str]) {
+    let values: Vec<(String, Value)> = if fields.is_empty() {
+        record.fields.as_object().into_iter().flatten().map(|(name, value)| (name.clone(), value.clone())).collect()
+    } else {
+        fields
+            .iter()
+            .map(|field| (field.to_string(), field_path_value(&record.fields, field).unwrap_or(Value::Null)))
+            .collect()
+    };
+
+    let mut used = vec!["RAU_ID".to_string()];
+    println!("RAU_ID={}", shell_quote(&record.id));
+    for (name, value) in values {
+        let sanitized: String = name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' }).collect();
+        let variable = unique_identifier(format!("RAU_{}", sanitized), &mut used);
+        let text = match value {
+            Value::String(text) => text,
+            Value::Null => String::new(),
+            other => other.to_string(),
+        };
+        println!("{}={}", variable, shell_quote(&text));
+    }
+}
+
+// Print a queried record: every field, or only the named ones, and its web URL if given
+fn print_queried_record(record: &Record, fields: &[&str], url: Option<&str>, opts: &Options) -> serde_json::Result<()> {
+    if opts.shell_vars {
+        print_shell_vars(record, fields);
+    } else if fields.is_empty() && opts.oneline {
+        println!("{}", oneline_record(record, opts.oneline_width));
+    } else {
+        return opts.format.render_record(record, fields, url, opts);
+    }
+    if let Some(url) = url {
+        print_url(url);
+    }
+    Ok(())
+}
+
+// Interpret the escapes \t, \n, \0 and \\ in a separator given on the command line
+fn unescape_separator(separator: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = separator.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some('0') => unescaped.push('\0'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+// Bucket records by their value in `field`, keeping groups in order of first appearance
+fn group_records<'r>(records: &'r [Record], field: &str) -> Vec<(String, Vec<&'r Record>)> {
+    let mut groups: Vec<(String, Vec<&Record>)> = Vec::new();
+    for record in records {
+        let key = match record.fields.get(field) {
+            None | Some(Value::Null) => "(empty)".to_string(),
+            Some(Value::String(text)) => text.clone(),
+            Some(Value::Array(items)) => items
+                .iter()
+                .map(|item| item.as_str().map(str::to_string).unwrap_or_else(|| item.to_string()))
+                .collect::<Vec<_>>()
+                .join(", "),
+            Some(other) => other.to_string(),
+        };
+        match groups.iter_mut().find(|(existing, _)| *existing == key) {
+            Some((_, members)) => members.push(record),
+            None => groups.push((key, vec![record])),
+        }
+    }
+    groups
+}
+
+// Use an explicitly given key field, falling back to the table's configured `key_field`. Either
+// must be a field of the table.
+fn key_field_or<'a>(field: &'a str, table_config: &'a TableConfig, available_fields: &[Field], flag: &str) -> Result<&'a str, Box<dyn std::error::Error>> {
+    let key = match field {
+        "" => table_config
+            .key_field
+            .as_deref()
+            .ok_or_else(|| RauError::Usage(format!("{} needs a field name or a key_field in the table config", flag)))?,
+        field => field,
+    };
+    if !available_fields.iter().any(|available| available.name == key) {
+        return Err(RauError::Usage(format!("{}: '{}' is not a field of {}", flag, key, table_config.table_name)).into());
+    }
+    Ok(key)
+}
+
+// Collapse records sharing a value in `field`, keeping the first seen or the most recently created.
+// Records without a value in the field are never treated as duplicates.
+fn dedup_records(records: Vec<Record>, field: &str, keep_newest: bool) -> Vec<Record> {
+    let mut kept: Vec<Record> = Vec::new();
+    let mut positions: HashMap<String, usize> = HashMap::new();
+    for record in records {
+        let key = match record.fields.get(field) {
+            Some(Value::Null) | None => {
+                kept.push(record);
+                continue;
+            }
+            Some(value) => value.to_string(),
+        };
+        match positions.get(&key) {
+            Some(&position) => {
+                if keep_newest && record.created_time > kept[position].created_time {
+                    kept[position] = record;
+                }
+            }
+            None => {
+                positions.insert(key, kept.len());
+                kept.push(record);
+            }
+        }
+    }
+    kept
+}
+
+// Describe the requests an operation would make, in the order they would be sent, and how they
+// are retried and paced
+fn plan_text(api_url: &str, table_config: &TableConfig, opts: &Options) -> String {
+    let records_url = format!("{}/v0/{}/{}", api_url, table_config.base_id, table_config.table_name);
+    let mut steps = Vec::new();
+    if opts.assume_schema.is_none() {
+        steps.push(format!("GET {}/v0/meta/bases/{}/tables (refresh the schema cache)", api_url, table_config.base_id));
+    }
+
+    if opts.options_field.is_some() || opts.output_schema || opts.output_fields || matches!(opts.command, Some("json-schema" | "codegen" | "list-views")) {
+        // Served from the schema cache
+    } else if opts.command == Some("copy") {
+        let source_id = opts.fields.first().map(|id| id.to_string()).unwrap_or_default();
+        steps.push(format!("GET {}/{} (read the source record)", records_url, source_id));
+        if let Some(to) = opts.copy_to {
+            steps.push(format!("GET the {} schema (find the destination's updatable fields)", to));
+        }
+        steps.push(format!("POST the destination records endpoint (create the copy of {})", source_id));
+    } else if opts.command == Some("download-attachments") {
+        steps.push(format!("GET {} (list every matching record's attachment field, one request per 100 records)", records_url));
+        steps.push(format!("GET each attachment URL, up to {} at a time (without the API key)", opts.max_concurrency));
+    } else if opts.command == Some("rename-field") {
+        let field_name = opts.fields.first().copied().unwrap_or("<old_name>");
+        steps.push(format!(
+            "PATCH {}/v0/meta/bases/{}/tables/<table_id>/fields/<id of {}> (rename the field, then remove the schema cache)",
+            api_url, table_config.base_id, field_name
+        ));
+    } else if opts.command == Some("diff-records") || (opts.record_id.is_some() && opts.compare_to.is_some()) {
+        steps.push(format!("GET {}/<record_id> x 2 (read both records to compare)", records_url));
+    } else if opts.command == Some("get") {
+        let chunks = opts.fields.len().div_ceil(IDS_PER_REQUEST);
+        steps.push(format!(
+            "GET {} with filterByFormula OR(RECORD_ID()=...) x {} (up to {} IDs per request; POST {}/listRecords if the URL is too long)",
+            records_url, chunks, IDS_PER_REQUEST, records_url
+        ));
+    } else if opts.command == Some("create") {
+        if opts.upsert_on.is_some() || opts.on_conflict.is_some() {
+            steps.push(format!("GET {} with filterByFormula on the key field (find records that already exist)", records_url));
+        }
+        steps.push(format!("POST {} (create the record, or PATCH it if it exists and conflicts overwrite)", records_url));
+    } else if opts.command == Some("import") {
+        if opts.upsert_on.is_some() || opts.on_conflict.is_some() {
+            steps.push(format!("GET {} with filterByFormula on the key field (per {} rows, find records that already exist)", records_url, IMPORT_CHUNK_ROWS));
+        }
+        steps.push(format!("POST {} (create the rows as they are read, {} per request)", records_url, WRITE_BATCH_SIZE));
+    } else if opts.command == Some("set") {
+        steps.push(format!("GET {} (list every record matching --filter, one request per 100 records)", records_url));
+        if !opts.dry_run {
+            steps.push(format!("PATCH {} (update the matching records, {} per request)", records_url, WRITE_BATCH_SIZE));
+        }
+    } else if opts.command == Some("export-sqlite") {
+        steps.push(format!("GET {} (list every record, one request per 100 records)", records_url));
+    } else if opts.command == Some("export-csv") {
+        if !opts.expand_links.is_empty() {
+            steps.push(format!("GET {}/v0/meta/bases/{}/tables (find the primary fields of linked tables)", api_url, table_config.base_id));
+        }
+        steps.push(format!("GET {} (list every record, one request per 100 records)", records_url));
+        for field in &opts.expand_links {
+            steps.push(format!(
+                "GET the table {} links to, with filterByFormula OR(RECORD_ID()=...) (per page, for up to {} new linked IDs per request)",
+                field, IDS_PER_REQUEST
+            ));
+        }
+    } else if opts.output_recent || opts.command == Some("validate-records") {
+        if opts.all && opts.parallel_scan {
+            steps.push(format!(
+                "GET {} x {} partitions by record ID, up to {} at a time (one request per 100 records in each)",
+                records_url,
+                RECORD_ID_CHARS.len(),
+                opts.max_concurrency
+            ));
+        } else if opts.all {
+            steps.push(format!("GET {} (list every matching record, one request per 100 records)", records_url));
+        } else {
+            steps.push(format!("GET {}?maxRecords=100 (list up to 100 records)", records_url));
+        }
+    } else if let Some(record_id) = opts.record_id {
+        if opts.is_write() {
+            let uploads: Vec<&str> = opts.fields.iter().filter_map(|field| parse_inline_attachment(field)).map(|(name, _)| name).collect();
+            let updates = opts.fields.len() - uploads.len();
+            if opts.only_changed || opts.merge_arrays || opts.if_match.is_some() || opts.fields.iter().any(|field| parse_array_edit(field).is_some()) {
+                steps.push(format!("GET {}/{} (read current values for +=, -=, --merge-arrays, --if-match and --only-changed)", records_url, record_id));
+            }
+            if updates > 0 {
+                steps.push(format!("PATCH {} (update {} field(s) on {})", records_url, updates, record_id));
+            }
+            for name in uploads {
+                steps.push(format!(
+                    "POST https://content.airtable.com/v0/{}/{}/{}/uploadAttachment (upload attachment)",
+                    table_config.base_id, record_id, name
+                ));
+            }
+            if updates > 0 && opts.verify {
+                steps.push(format!("GET {}/{} (re-read the record for --verify)", records_url, record_id));
+            }
+        } else {
+            steps.push(format!("GET {}/{} (query record)", records_url, record_id));
+        }
+    } else {
+        steps.push(format!("POST {} (create a record with every updatable field empty)", records_url));
+    }
+
+    let mut plan = String::from("Plan:\n");
+    for (index, step) in steps.iter().enumerate() {
+        plan.push_str(&format!("  {}. {}\n", index + 1, step));
+    }
+    plan.push_str(&format!("Requests: {}\n", steps.len()));
+    let retry = &opts.retry;
+    if retry.max_retries == 0 {
+        plan.push_str("Retries: none, failed requests are reported without retrying\n");
+    } else {
+        plan.push_str(&format!(
+            "Retries: up to {} per request for 429s, and for 5xx responses and connection failures of GETs, backing off from {}ms to at most {}ms{}\n",
+            retry.max_retries,
+            retry.base_ms,
+            retry.max_ms,
+            if retry.jitter { " with full jitter" } else { "" }
+        ));
+    }
+    match opts.base_delay {
+        Some(ms) => plan.push_str(&format!("Pacing: {}ms before every request, including retries\n", ms)),
+        None => plan.push_str("Pacing: none\n"),
+    }
+    if let Some(limit) = opts.request_limit {
+        plan.push_str(&format!("Request limit: {} (--limit-requests)\n", limit));
+    }
+    plan.push_str(&format!("Concurrency: up to {} requests at a time for --parallel-scan and download-attachments, otherwise one at a time\n", opts.max_concurrency));
+    plan
+}
+
+fn print_plan(api_url: &str, table_config: &TableConfig, opts: &Options) {
+    print!("{}", plan_text(api_url, table_config, opts));
+}
+
+// Let in-flight requests finish on the first Ctrl-C and abort immediately on the second
+fn install_interrupt_handler() {
+    tokio::spawn(async {
+        if tokio::signal::ctrl_c().await.is_err() {
+            return;
+        }
+        INTERRUPTED.store(true, Ordering::SeqCst);
+        eprintln!("Interrupted: finishing in-flight requests (press Ctrl-C again to abort)");
+        if tokio::signal::ctrl_c().await.is_ok() {
+            std::process::exit(130);
+        }
+    });
+}
+
+fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+// Send an arbitrary authenticated request to the Airtable API and print the raw response
+async fn raw_request(client: &AirtableClient, matches: &clap::ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let method: Method = matches.get_one::<String>("method").expect("method has a default").to_uppercase().parse()?;
+    let path = matches.get_one::<String>("path").ok_or_else(|| RauError::Usage("Usage: rau raw-request --path /v0/... [--method GET] [--body @file.json]".to_string()))?;
+    if !path.starts_with('/') {
+        return Err("--path must start with '/' and is relative to the Airtable API host".into());
+    }
+    if method != Method::GET && method != Method::HEAD && !matches.get_flag("yes") {
+        return Err(format!("{} requests can modify data; pass --yes to send it", method).into());
+    }
+
+    let mut request = client.request(method, format!("{}{}", client.api_url, path));
+    if let Some(body) = matches.get_one::<String>("body") {
+        let body = match body.strip_prefix('@') {
+            Some(file) => fs::read_to_string(file)?,
+            None => body.clone(),
+        };
+        request = request.header("Content-Type", "application/json").body(body);
+    }
+
+    let (status, text) = client.send(request).await?;
+    eprintln!("Status: {}", status);
+    println!("{}", text);
+    if !status.is_success() {
+        return Err(RauError::from_status(status, format!("Request failed with status {}", status)));
+    }
+    Ok(())
+}
+
+// Resolve the configuration names to operate on, expanding globs when requested
+fn matching_table_names<'a>(config: &'a Settings, config_name: &'a str, use_glob: bool) -> Result<Vec<&'a str>, Box<dyn std::error::Error>> {
+    if !use_glob {
+        if !config.tables.contains_key(config_name) {
+            return Err(RauError::Usage(format!("Configuration '{}' not found in config", config_name)).into());
+        }
+        return Ok(vec![config_name]);
+    }
+
+    let pattern = glob::Pattern::new(config_name)?;
+    let mut names: Vec<&str> = config
+        .tables
+        .keys()
+        .filter(|name| pattern.matches(name))
+        .map(|name| name.as_str())
+        .collect();
+    names.sort();
+
+    if names.is_empty() {
+        return Err(RauError::Usage(format!("No configurations match '{}'", config_name)).into());
+    }
+    Ok(names)
+}
+
+fn main() {
+    // Create CLI interface
+    let matches = build_cli().get_matches();
+
+    // One-shot queries the blocking path can serve skip starting the async runtime
+    #[cfg(feature = "blocking")]
+    if let Some(result) = blocking::try_run(&matches) {
+        return exit_on_error(result);
+    }
+
+    exit_on_error(tokio::runtime::Runtime::new().map_err(Into::into).and_then(|runtime| runtime.block_on(run(matches))));
+}
+
+// Print a failed run's error and exit with its code
+fn exit_on_error(result: Result<(), Box<dyn std::error::Error>>) {
+    if let Err(err) = result {
+        eprintln!("Error: {}", err);
+        std::process::exit(exit_code(err.as_ref()));
+    }
+}
+
+async fn run(matches: clap::ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let started = Instant::now();
+
+    let config_name = matches.get_one::<String>("config").expect("Configuration name is required");
+    if config_name == "doctor" {
+        return doctor(&matches).await;
+    }
+
+    // Load configuration
+    let config = Settings::new(!matches.get_flag("no_env"), matches.get_one::<String>("config_format").map(String::as_str))?;
+
+    if config_name == "set-key" {
+        return set_keyring_key(&config);
+    }
+
+    // Global defaults apply to the client and the run as a whole; table defaults are added per table below
+    let global_matches = with_defaults(&matches, &config.defaults, &HashMap::new())?;
+    let run_matches = global_matches.as_ref().unwrap_or(&matches);
+
+    let mut client = AirtableClient::new(&config.api_key()?);
+    client.verbose = run_matches.get_flag("verbose");
+    client.strict_response = run_matches.get_flag("strict_response");
+    client.quiet_success = run_matches.get_flag("quiet_success");
+    let header_flags: Vec<&str> = run_matches.get_many::<String>("header").unwrap_or_default().map(String::as_str).collect();
+    client.headers = config.request_headers(&header_flags)?;
+    client.request_limit = run_matches.get_one::<usize>("limit_requests").copied();
+    client.max_body_size = run_matches.get_one::<usize>("max_body_size").copied();
+    client.base_delay = run_matches.get_one::<u64>("base_delay").map(|ms| Duration::from_millis(*ms));
+    client.curl = run_matches.get_flag("curl");
+    client.retry = RetryPolicy::from_matches(run_matches)?;
+    client.audit_log = run_matches.get_one::<String>("audit_log").cloned().or_else(|| config.audit_log.clone());
+    client.progress_json = run_matches.get_flag("progress_json");
+
+    if config_name == "raw-request" {
+        return raw_request(&client, run_matches).await;
+    }
+    let mut opts = Options::from_matches(run_matches)?;
+    opts.resolve_filter(&config.filters)?;
+
+    let table_names = matching_table_names(&config, config_name, run_matches.get_flag("glob"))?;
+    if table_names.len() > 1 && opts.is_write() && !opts.explain && !run_matches.get_flag("yes") {
+        return Err(RauError::Usage(format!(
+            "'{}' matches {} configurations ({}). Pass --yes to write to all of them.",
+            config_name,
+            table_names.len(),
+            table_names.join(", ")
+        ))
+        .into());
+    }
+
+    install_interrupt_handler();
+
+    let result = run_tables(&client, &config, &matches, &table_names).await;
+    // An interrupted run still reports how far it got
+    let interrupted = matches!(&result, Err(err) if matches!(err.downcast_ref::<RauError>(), Some(RauError::Interrupted(_))));
+    if result.is_ok() || interrupted {
+        client.print_summary(started, table_names.len());
+    }
+    result
+}
+
+// Run the operation against each matched configuration with its table defaults
+async fn run_tables(client: &AirtableClient, config: &Settings, matches: &clap::ArgMatches, table_names: &[&str]) -> Result<(), Box<dyn std::error::Error>> {
+    for (index, name) in table_names.iter().enumerate() {
+        if table_names.len() > 1 {
+            eprintln!("== {} ==", name);
+        }
+        let table_config = &config.tables[*name];
+        let table_matches = with_defaults(matches, &config.defaults, &table_config.defaults)?;
+        let mut opts = Options::from_matches(table_matches.as_ref().unwrap_or(matches))?;
+        opts.apply_table_defaults(table_config);
+        opts.resolve_filter(&config.filters)?;
+        run_table(client, config, table_config, &opts).await?;
+
+        // Listings stop after the page in flight on Ctrl-C and print what they fetched, so
+        // what was printed may be partial
+        if interrupted() {
+            let mut message = format!("Interrupted: the output of {} may be partial", name);
+            if index + 1 < table_names.len() {
+                message += &format!(". Stopped after {} of {} configurations. Not run: {}", index + 1, table_names.len(), table_names[index + 1..].join(", "));
+            }
+            return Err(RauError::Interrupted(message).into());
+        }
+    }
+    Ok(())
+}
+
+// Flags read before the config is loaded, which config defaults cannot set
+const CONFIG_LOADING_FLAGS: &[&str] = &["no-env", "config-format"];
+
+// Flags that configure the HTTP client or the run as a whole, so they can only be set in the
+// global `defaults`, not per table
+const RUN_FLAGS: &[&str] = &[
+    "verbose",
+    "strict-response",
+    "quiet-success",
+    "header",
+    "limit-requests",
+    "max-body-size",
+    "base-delay",
+    "curl",
+    "max-retries",
+    "retry-base-ms",
+    "retry-max-ms",
+    "retry-jitter",
+    "progress-json",
+    "audit-log",
+    "glob",
+    "yes",
+];
+
+// Flags that only mean something alongside another, as declared with `.requires` in
+// build_cli. A default for one is skipped unless the other is given or defaulted too.
+const REQUIRES: &[(&str, &str)] = &[("parallel_scan", "all"), ("seed", "sample")];
+
+// Re-parse the command line with config defaults for flags that were not given explicitly.
+// Precedence is explicit flag > table `defaults` > global `defaults` > built-in default.
+fn with_defaults(
+    matches: &clap::ArgMatches,
+    global: &HashMap<String, Value>,
+    table: &HashMap<String, Value>,
+) -> Result<Option<clap::ArgMatches>, Box<dyn std::error::Error>> {
+    let extra = default_args(matches, global, table)?;
+    if extra.is_empty() {
+        return Ok(None);
+    }
+
+    // Defaults go straight after the program name so they never land after a `--`
+    let mut args: Vec<std::ffi::OsString> = env::args_os().collect();
+    args.splice(1..1, extra.into_iter().map(Into::into));
+    Ok(Some(build_cli().try_get_matches_from(args)?))
+}
+
+// The `--flag=value` arguments for the defaults that apply. A default gives way to its own
+// flag on the command line and to any flag given there that it conflicts with.
+fn default_args(
+    matches: &clap::ArgMatches,
+    global: &HashMap<String, Value>,
+    table: &HashMap<String, Value>,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    for flag in global.keys().chain(table.keys()) {
+        let name = flag.trim_start_matches('-');
+        if CONFIG_LOADING_FLAGS.contains(&name) {
+            return Err(RauError::Usage(format!("'--{}' cannot be set in config defaults, since it controls how the config is loaded", name)).into());
+        }
+    }
+    if let Some(flag) = table.keys().find(|flag| RUN_FLAGS.contains(&flag.trim_start_matches('-'))) {
+        return Err(RauError::Usage(format!("'--{}' applies to the whole run and can only be set in the global [defaults], not a table's", flag.trim_start_matches('-'))).into());
+    }
+    let mut defaults: Vec<(&String, &Value)> = global.iter().filter(|(flag, _)| !table.contains_key(*flag)).chain(table.iter()).collect();
+    defaults.sort_by_key(|(flag, _)| *flag);
+
+    let cli = build_cli();
+    let mut resolved = Vec::new();
+    for (flag, value) in defaults {
+        let name = flag.trim_start_matches('-');
+        let arg = cli
+            .get_arguments()
+            .find(|arg| arg.get_long() == Some(name))
+            .ok_or_else(|| format!("Unknown flag '--{}' in config defaults", name))?;
+        resolved.push((arg, value));
+    }
+
+    let given = |id: &str| matches.value_source(id) == Some(clap::parser::ValueSource::CommandLine);
+    resolved.retain(|(arg, _)| {
+        // Conflicts are declared on one side only, so check both directions
+        let mut conflicts = cli
+            .get_arguments()
+            .filter(|other| cli.get_arg_conflicts_with(arg).contains(other) || cli.get_arg_conflicts_with(other).contains(arg));
+        !given(arg.get_id().as_str()) && !conflicts.any(|other| given(other.get_id().as_str()))
+    });
+    let defaulted = |id: &str| resolved.iter().any(|(arg, value)| arg.get_id() == id && !matches!(value, Value::Bool(false) | Value::Null));
+    let mut extra = Vec::new();
+    for (arg, value) in &resolved {
+        let id = arg.get_id().as_str();
+        if REQUIRES.iter().any(|(flag, required)| *flag == id && !given(required) && !defaulted(required)) {
+            continue;
+        }
+
+        let name = arg.get_long().unwrap_or(id);
+        let values = match value {
+            Value::Bool(true) => vec![None],
+            Value::Bool(false) | Value::Null => vec![],
+            Value::Array(items) => items.iter().map(|item| Some(item.as_str().map(String::from).unwrap_or_else(|| item.to_string()))).collect(),
+            Value::String(text) => vec![Some(text.clone())],
+            other => vec![Some(other.to_string())],
+        };
+        for value in values {
+            extra.push(match value {
+                Some(value) => format!("--{}={}", name, value),
+                None => format!("--{}", name),
+            });
+        }
+    }
+    Ok(extra)
+}
+
+async fn run_table(client: &AirtableClient, config: &Settings, table_config: &TableConfig, opts: &Options<'_>) -> Result<(), Box<dyn std::error::Error>> {
+    if opts.explain {
+        print_plan(&client.api_url, table_config, opts);
+        return Ok(());
+    }
+
+    let record_id = opts.record_id;
+    let fields = &opts.fields;
+
+    let cached_table = match opts.assume_schema {
+        // Use the given schema instead of fetching it
+        Some(path) => load_assumed_schema(path, table_config)?,
+        None => {
+            // Cache available fields to a local file
+            let cache_file = &config.paths.cache_file;
+            refresh_schema_cache(client, table_config, cache_file, opts.startup_retries).await?;
+
+            // Read available fields from cache
+            read_cached_table(cache_file)?
+        }
+    };
+    let available_fields = cached_table.fields;
+
+    if let Some(field_name) = opts.options_field {
+        // Output the choices of a select field
+        let field = available_fields
+            .iter()
+            .find(|field| &field.name == field_name)
+            .ok_or_else(|| format!("Field '{}' not found in schema", field_name))?;
+        if field.field_type != "singleSelect" && field.field_type != "multipleSelects" {
+            return Err(format!("Field '{}' is a {} field, not a select field", field_name, field.field_type).into());
+        }
+
+        let choices = field
+            .options
+            .as_ref()
+            .and_then(|options| options.get("choices"))
+            .and_then(|choices| choices.as_array())
+            .cloned()
+            .unwrap_or_default();
+        opts.format.render_choices(&choices, opts)?;
+        return Ok(());
+    }
+
+    if opts.output_schema {
+        // Output the schema
+        if opts.markdown {
+            println!("| Name | Type | Description |");
+            println!("| --- | --- | --- |");
+            for field in &available_fields {
+                println!(
+                    "| {} | {} | {} |",
+                    markdown_cell(&field.name),
+                    markdown_cell(&field.field_type),
+                    markdown_cell(field.description.as_deref().unwrap_or_default())
+                );
+            }
+            return Ok(());
+        }
+        let schema_json = serde_json::to_string_pretty(&available_fields)?;
+        println!("{}", schema_json);
+        return Ok(());
+    }
+
+    // Filter out computed fields
+    let updatable_fields: Vec<String> = available_fields
+        .iter()
+        .filter(|field| !is_computed_type(&field.field_type))
+        .map(|field| field.name.clone())
+        .collect();
+
+    if opts.command == Some("export-sqlite") {
+        let path = opts.fields.first().ok_or_else(|| RauError::Usage("Usage: rau <config> export-sqlite <file.db>".to_string()))?;
+        let renames = match opts.rename_map {
+            Some(map_path) => load_rename_map(map_path, &available_fields, opts.ignore_unknown)?,
+            None => HashMap::new(),
+        };
+        return export_sqlite(client, table_config, &available_fields, &renames, path).await;
+    }
+
+    if opts.command == Some("export-csv") {
+        let path = opts.fields.first().ok_or_else(|| RauError::Usage("Usage: rau <config> export-csv <file.csv> (or - for stdout)".to_string()))?;
+        let renames = match opts.rename_map {
+            Some(map_path) => load_rename_map(map_path, &available_fields, opts.ignore_unknown)?,
+            None => HashMap::new(),
+        };
+        return export_csv(client, table_config, &available_fields, &renames, &opts.expand_links, path).await;
+    }
+
+    if opts.command == Some("diff-records") {
+        let (id_a, id_b) = match opts.fields.as_slice() {
+            [id_a, id_b] => (*id_a, *id_b),
+            _ => return Err(RauError::Usage("Usage: rau <config> diff-records <record_id_a> <record_id_b>".to_string()).into()),
+        };
+        return diff_records(client, table_config, id_a, id_b, opts).await;
+    }
+    if let (Some(record_id), Some(compare_to)) = (record_id, opts.compare_to) {
+        return diff_records(client, table_config, record_id, compare_to, opts).await;
+    }
+
+    if opts.command == Some("download-attachments") {
+        let usage = || RauError::Usage("Usage: rau <config> download-attachments --field <FIELD> --dir <DIR> [--filter <FORMULA>]".to_string());
+        let field_name = opts.attachment_field.ok_or_else(usage)?;
+        let dir = opts.dir.ok_or_else(usage)?;
+        return download_attachments(client, table_config, &available_fields, field_name, dir, opts).await;
+    }
+
+    if opts.command == Some("rename-field") {
+        let (old_name, new_name) = match opts.fields.as_slice() {
+            [old_name, new_name] => (*old_name, *new_name),
+            _ => return Err(RauError::Usage("Usage: rau <config> rename-field <old_name> <new_name>".to_string()).into()),
+        };
+        return rename_field(client, table_config, &cached_table.id, &available_fields, &config.paths.cache_file, old_name, new_name).await;
+    }
+
+    if opts.command == Some("list-views") {
+        opts.format.render_views(&cached_table.views, opts)?;
+        return Ok(());
+    }
+
+    if opts.command == Some("codegen") {
+        print!("{}", rust_codegen(&cached_table.name, &available_fields));
+        return Ok(());
+    }
+
+    if opts.command == Some("json-schema") {
+        for field in &opts.require {
+            if !available_fields.iter().any(|available| available.name == *field) {
+                return Err(format!("Required field '{}' not found in schema", field).into());
+            }
+        }
+        let schema = json_schema(&cached_table.name, &available_fields, &opts.require);
+        println!("{}", serde_json::to_string_pretty(&schema)?);
+        return Ok(());
+    }
+
+    if opts.command == Some("create") {
+        let mut row = Map::new();
+        for field in &opts.fields {
+            add_field_assignment(&mut row, field).map_err(RauError::Usage)?;
+        }
+        let policy = ConflictPolicy::from_options(opts, table_config, &available_fields)?;
+        create_records(client, table_config, vec![(1, row)], policy.as_ref()).await?;
+        return Ok(());
+    }
+
+    if opts.command == Some("import") {
+        let policy = ConflictPolicy::from_options(opts, table_config, &available_fields)?;
+        return import_csv(client, table_config, &available_fields, opts, policy.as_ref()).await;
+    }
+
+    if opts.command == Some("set") {
+        return set_matching(client, table_config, &available_fields, opts).await;
+    }
+
+    if opts.command == Some("validate-records") {
+        let records = list_records(client, table_config, opts).await?;
+        let rules = ValidationRules::from_options(opts)?;
+        let mut violations = 0;
+        for record in &records {
+            let failures = rules.check(record);
+            if opts.ids_only && !failures.is_empty() {
+                println!("{}", record.id);
+            }
+            for failure in &failures {
+                if !opts.ids_only {
+                    println!("{}: {}", record.id, failure);
+                }
+                violations += 1;
+            }
+        }
+        if violations > 0 {
+            return Err(format!("Checked {} records, found {} violation(s)", records.len(), violations).into());
+        }
+        if !client.quiet_success {
+            eprintln!("Checked {} records, found {} violation(s)", records.len(), violations);
+        }
+        return Ok(());
+    }
+
+    if opts.command == Some("copy") {
+        let source_id = opts.fields.first().ok_or_else(|| RauError::Usage("Usage: rau <config> copy <record_id> [--to <config>]".to_string()))?;
+        return copy_record(client, config, table_config, &updatable_fields, source_id, opts.copy_to).await;
+    }
+
+    if opts.command == Some("get") {
+        // Record IDs come from the arguments, or from stdin one per line when given `-`
+        let ids: Vec<String> = if opts.fields == ["-"] {
+            io::stdin().lines().collect::<io::Result<Vec<String>>>()?.into_iter().map(|id| id.trim().to_string()).filter(|id| !id.is_empty()).collect()
+        } else {
+            opts.fields.iter().map(|id| id.to_string()).collect()
+        };
+        if ids.is_empty() {
+            return Err(RauError::Usage("Usage: rau <config> get <record_id>... (or - to read IDs from stdin)".to_string()).into());
+        }
+
+        let records = get_records(client, table_config, &ids).await?;
+        if opts.ids_only {
+            for record in &records {
+                println!("{}", record.id);
+            }
+        } else {
+            opts.format.render_full_records(&records, opts)?;
+        }
+        return Ok(());
+    }
+
+    if opts.output_fields {
+        // Output the updatable fields
+        let fields_json = serde_json::to_string_pretty(&updatable_fields)?;
+        println!("{}", fields_json);
+        return Ok(());
+    }
+
+    if opts.output_recent {
+        if opts.primary_only {
+            // Print only the primary field, fetching nothing else, a page at a time
+            let primary = available_fields
+                .iter()
+                .find(|field| field.id == cached_table.primary_field_id)
+                .map(|field| field.name.clone())
+                .ok_or("The schema does not identify the table's primary field")?;
+            let mut params = list_params(opts);
+            params.push(("fields[]".to_string(), primary.clone()));
+            let records_url = format!("{}/v0/{}/{}", client.api_url, table_config.base_id, table_config.table_name);
+            let mut pager = RecordPager::new(client, records_url, params);
+            while let Some(page) = pager.next_page().await? {
+                for record in page {
+                    println!("{}", record.fields.get(&primary).map(|value| display_value(value, opts.max_field_length)).unwrap_or_default());
+                }
+                if interrupted() {
+                    break;
+                }
+            }
+            return Ok(());
+        }
+
+        // Output the 100 most recent record IDs and their names
+        let mut records = match opts.sample {
+            Some(size) => sample_records(client, table_config, opts, size).await?,
+            None => list_records(client, table_config, opts).await?,
+        };
+        if let Some(dedup_field) = opts.dedup_on {
+            let dedup_field = key_field_or(dedup_field, table_config, &available_fields, "--dedup-on")?;
+            let before = records.len();
+            records = dedup_records(records, dedup_field, opts.dedup_keep == "newest");
+            eprintln!("Skipped {} duplicate record(s) on {}", before - records.len(), dedup_field);
+        }
+        if opts.ids_only {
+            for record in &records {
+                println!("{}", record.id);
+            }
+            return Ok(());
+        }
+        match opts.group_by {
+            Some(group_field) => opts.format.render_groups(&group_records(&records, group_field), opts)?,
+            None => opts.format.render_records(&records, opts)?,
+        }
+
+        return Ok(());
+    }
+
+    let update_record_url = format!("{}/v0/{}/{}", client.api_url, table_config.base_id, table_config.table_name);
+
+    if let Some(record_id) = record_id {
+        if fields.is_empty() {
+            // Query all fields for their values
+            let query_record_url = format!("{}/{}", update_record_url, record_id);
+
+            // Make the API request
+            let (status, text) = client.send(client.get(&query_record_url)).await?;
+
+            if status.is_success() {
+                let mut record: Record = client.parse_record(&text)?;
+                if opts.no_computed {
+                    remove_computed_fields(&mut record, &available_fields);
+                }
+                let url = opts.record_url.then(|| record_url(&table_config.base_id, &cached_table.id, &record.id)).flatten();
+                print_queried_record(&record, fields, url.as_deref(), opts)?;
+            } else {
+                return Err(RauError::from_status(status, format!("Failed to query record. Status: {}, Response: {}", status, text)));
+            }
+        } else {
+            // Check if fields are in key=value format or not
+            let is_update = fields.iter().any(|field| is_update_arg(field));
+
+            if is_update {
+                // Parse fields into a JSON object, setting inline attachments aside for upload
+                let mut fields_json = Map::new();
+                let mut uploads = Vec::new();
+                let mut array_edits = Vec::new();
+                for field in fields {
+                    if let Some((name, data)) = parse_inline_attachment(field) {
+                        uploads.push((name, data));
+                        continue;
+                    }
+                    if let Some(edit) = parse_array_edit(field) {
+                        array_edits.push(edit);
+                        continue;
+                    }
+                    add_field_assignment(&mut fields_json, field).map_err(RauError::Usage)?;
+                }
+
+                let if_match: Option<Map<String, Value>> = match opts.if_match {
+                    Some(expected) => Some(
+                        serde_json::from_str(expected).map_err(|err| RauError::Usage(format!("--if-match must be a JSON object of field values: {}", err)))?,
+                    ),
+                    None => None,
+                };
+
+                if !uploads.is_empty() && opts.content_type.is_none() {
+                    return Err(RauError::Usage("--content-type is required for @base64: attachments".to_string()).into());
+                }
+                for (name, data) in &uploads {
+                    BASE64.decode(data).map_err(|err| RauError::Usage(format!("Invalid base64 content for {}: {}", name, err)))?;
+                }
+
+                // With --merge-arrays, plain assignments to multi-value fields add to the current values
+                let merged_fields = merged_array_fields(&available_fields, &fields_json, opts.merge_arrays);
+
+                if !array_edits.is_empty() || !merged_fields.is_empty() || opts.only_changed || if_match.is_some() {
+                    // Read the current values so arrays can be merged into them, and --only-changed can compare
+                    let query_record_url = format!("{}/{}", update_record_url, record_id);
+                    let (status, text) = client.send(client.get(&query_record_url)).await?;
+
+                    if !status.is_success() {
+                        return Err(RauError::from_status(status, format!("Failed to query record. Status: {}, Response: {}", status, text)));
+                    }
+                    let record: Record = client.parse_record(&text)?;
+                    if let Some(expected) = &if_match {
+                        // Refuse to overwrite an edit made since the caller read the record
+                        let changed: Vec<String> = expected
+                            .iter()
+                            .filter(|(name, value)| !same_field_value(value, record.fields.get(name.as_str())))
+                            .map(|(name, value)| format!("{} is {} (expected {})", name, record.fields.get(name.as_str()).unwrap_or(&Value::Null), value))
+                            .collect();
+                        if !changed.is_empty() {
+                            return Err(RauError::Conflict(format!("Record {} was not updated: {}", record_id, changed.join("; "))).into());
+                        }
+                    }
+                    merge_into_current(&mut fields_json, &merged_fields, &record);
+                    for (name, remove, value) in array_edits {
+                        let current = fields_json.get(name).or_else(|| record.fields.get(name));
+                        let merged = merge_array_value(current, parse_json_string(value), remove);
+                        fields_json.insert(name.to_string(), merged);
+                    }
+                    if opts.only_changed {
+                        let before = fields_json.len();
+                        fields_json.retain(|name, value| !same_field_value(value, record.fields.get(name)));
+                        if fields_json.is_empty() && uploads.is_empty() {
+                            if !client.quiet_success {
+                                eprintln!("Record unchanged, skipped the update");
+                            }
+                            return Ok(());
+                        }
+                        if fields_json.len() < before && !client.quiet_success {
+                            eprintln!("Skipped {} unchanged field(s)", before - fields_json.len());
+                        }
+                    }
+                }
+
+                if !fields_json.is_empty() {
+                    // Update existing record
+                    let update_data = json!({
+                        "records": [
+                            {
+                                "id": record_id,
+                                "fields": Value::Object(fields_json.clone())
+                            }
+                        ]
+                    });
+
+                    // Make the API request
+                    let request = client
+                        .patch(&update_record_url)
+                        .header("Content-Type", "application/json")
+                        .json(&update_data);
+                    let (status, text) = client.send(request).await?;
+
+                    if status.is_success() {
+                        let _updated_records: RecordsResponse = client.parse(&text)?;
+                        client.audit(table_config, "update", record_id, &Value::Object(fields_json.clone()));
+                        if !client.quiet_success {
+                            println!("Updated Record");
+                        }
+                    } else {
+                        return Err(RauError::from_status(status, format!("Failed to update record. Status: {}, Response: {}", status, text)));
+                    }
+                }
+
+                for (index, (name, data)) in uploads.iter().enumerate() {
+                    if interrupted() {
+                        let remaining: Vec<&str> = uploads[index..].iter().map(|(name, _)| *name).collect();
+                        return Err(RauError::Interrupted(format!("Interrupted before uploading to: {}", remaining.join(", "))).into());
+                    }
+                    let content_type = opts.content_type.map(String::as_str).unwrap_or_default();
+                    upload_attachment(client, &table_config.base_id, record_id, name, content_type, opts.filename, data).await?;
+                    client.audit(table_config, "upload", record_id, &json!({ *name: opts.filename }));
+                }
+
+                client.records_affected.fetch_add(1, Ordering::SeqCst);
+                if opts.record_url {
+                    print_record_url(&table_config.base_id, &cached_table.id, record_id);
+                }
+
+                if opts.verify && !fields_json.is_empty() {
+                    // Re-read the record and compare what was stored with what was sent
+                    let query_record_url = format!("{}/{}", update_record_url, record_id);
+                    let (status, text) = client.send(client.get(&query_record_url)).await?;
+                    if !status.is_success() {
+                        return Err(RauError::from_status(status, format!("Failed to re-read record for --verify. Status: {}, Response: {}", status, text)));
+                    }
+                    let record: Record = client.parse_record(&text)?;
+
+                    let mut unexpected = 0;
+                    for (name, sent) in &fields_json {
+                        let stored = record.fields.get(name);
+                        if same_field_value(sent, stored) {
+                            continue;
+                        }
+                        let computed = available_fields.iter().any(|field| &field.name == name && is_computed_type(&field.field_type));
+                        let stored = stored.map(Value::to_string).unwrap_or_else(|| "<no value>".to_string());
+                        if computed {
+                            eprintln!("Verify: {} is computed and reads back as {} (sent {})", name, stored, sent);
+                        } else {
+                            eprintln!("Verify: {} reads back as {} but {} was sent", name, stored, sent);
+                            unexpected += 1;
+                        }
+                    }
+                    if unexpected > 0 {
+                        return Err(format!("Verify failed: {} field(s) did not persist as sent", unexpected).into());
+                    }
+                    if !client.quiet_success {
+                        eprintln!("Verified {} field(s)", fields_json.len());
+                    }
+                }
+            } else {
+                // Query specific fields for their values
+                let query_record_url = format!("{}/{}", update_record_url, record_id);
+
+                // Make the API request
+                let (status, text) = client.send(client.get(&query_record_url)).await?;
+
+                if status.is_success() {
+                    let record: Record = client.parse_record(&text)?;
+                    let url = opts.record_url.then(|| record_url(&table_config.base_id, &cached_table.id, &record.id)).flatten();
+                    print_queried_record(&record, fields, url.as_deref(), opts)?;
+                } else {
+                    return Err(RauError::from_status(status, format!("Failed to query record. Status: {}, Response: {}", status, text)));
+                }
+            }
+        }
+    } else {
+        // Create new record with empty structure
+        let empty_fields: Map<String, Value> = updatable_fields.into_iter().map(|f| (f, json!(null))).collect();
+        let create_data = json!({
+            "records": [
+                {
+                    "fields": Value::Object(empty_fields)
+                }
+            ]
+        });
+
+        // Make the API request
+        let request = client
+            .post(&update_record_url)
+            .header("Content-Type", "application/json")
+            .json(&create_data);
+        let (status, text) = client.send(request).await?;
+
+        if status.is_success() {
+            let created_records: RecordsResponse = client.parse(&text)?;
+            if let Some(new_record) = created_records.records.first() {
+                if !client.quiet_success {
+                    eprintln!("Created Record ID");
+                    println!("{}", new_record.id);
+                }
+                client.records_affected.fetch_add(1, Ordering::SeqCst);
+                client.audit(table_config, "create", &new_record.id, &create_data["records"][0]["fields"]);
+                if opts.record_url {
+                    print_record_url(&table_config.base_id, &cached_table.id, &new_record.id);
+                }
+            } else {
+                return Err(format!("Failed to parse the response after creating a new record: {}", text).into());
+            }
+        } else {
+            return Err(RauError::from_status(status, format!("Failed to create record. Status: {}, Response: {}", status, text)));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_accepts_empty_bodies_unless_strict() {
+        let mut client = AirtableClient::new("key");
+        let empty: RecordsResponse = client.parse("").unwrap();
+        assert!(empty.records.is_empty());
+        let braces: RecordsResponse = client.parse(" {} ").unwrap();
+        assert!(braces.records.is_empty());
+
+        client.strict_response = true;
+        assert!(client.parse::<RecordsResponse>("").is_err());
+        assert!(client.parse::<RecordsResponse>("{}").is_err());
+        let page: RecordsResponse = client.parse(r#"{"records": [{"id": "rec1", "fields": {}}]}"#).unwrap();
+        assert_eq!(page.records[0].id, "rec1");
+    }
+
+    #[test]
+    fn parse_record_rejects_empty_bodies() {
+        let client = AirtableClient::new("key");
+        assert!(client.parse_record("").is_err());
+        assert!(client.parse_record("{}").is_err());
+        let record = client.parse_record(r#"{"id": "rec1", "fields": {"Name": "A"}}"#).unwrap();
+        assert_eq!(record.fields["Name"], "A");
+    }
+
+    #[test]
+    fn key_text_matches_numbers_across_representations() {
+        assert_eq!(key_text(&json!(42.0)), key_text(&json!(42)));
+        assert_eq!(key_text(&json!(42)), key_text(&json!("42")));
+        assert_eq!(key_text(&json!(4.5)), "4.5");
+        assert_eq!(key_text(&json!("Launch")), "Launch");
+    }
+
+    #[test]
+    fn key_clause_compares_as_text_and_escapes_the_field_name() {
+        assert_eq!(key_clause("Id", &json!(42.0)), "{Id}&''='42'");
+        assert_eq!(key_clause("a}b", &json!("x")), "{a\\}b}&''='x'");
+        assert_eq!(key_clause("Done", &json!(true)), "{Done}=TRUE()");
+    }
+
+    #[test]
+    fn parse_json_string_keeps_json_and_falls_back_to_text() {
+        assert_eq!(parse_json_string(r#"[{"url":"https://example.com/a.png"}]"#), json!([{ "url": "https://example.com/a.png" }]));
+        assert_eq!(parse_json_string("42"), json!(42));
+        assert_eq!(parse_json_string("hello world"), json!("hello world"));
+    }
+
+    #[test]
+    fn parse_attachment_url_splits_tilde_assignments() {
+        assert_eq!(parse_attachment_url("Image~=https://example.com/a.png"), Some(("Image", "https://example.com/a.png")));
+        assert_eq!(parse_attachment_url("Image=https://example.com/a.png"), None);
+        assert_eq!(parse_attachment_url("~=https://example.com/a.png"), None);
+    }
+
+    #[test]
+    fn add_field_assignment_collects_attachment_urls() {
+        let mut fields = Map::new();
+        add_field_assignment(&mut fields, "Image~=https://example.com/a.png").unwrap();
+        add_field_assignment(&mut fields, "Image~=https://example.com/b.png").unwrap();
+        add_field_assignment(&mut fields, r#"Files=[{"url":"https://example.com/c.pdf"}]"#).unwrap();
+        assert_eq!(fields["Image"], json!([{ "url": "https://example.com/a.png" }, { "url": "https://example.com/b.png" }]));
+        assert_eq!(fields["Files"], json!([{ "url": "https://example.com/c.pdf" }]));
+        assert!(add_field_assignment(&mut fields, "no-equals-sign").is_err());
+    }
+
+    fn table_with_defaults() -> TableConfig {
+        serde_json::from_value(json!({
+            "base_id": "appTEST",
+            "table_name": "Tasks",
+            "default_filter": "{Status} != 'Done'",
+            "default_sort": ["Priority:desc"],
+        }))
+        .unwrap()
+    }
+
+    fn options_for(args: &[&str], table_config: &TableConfig) -> String {
+        let matches = build_cli().try_get_matches_from(args).unwrap();
+        let mut opts = Options::from_matches(&matches).unwrap();
+        opts.apply_table_defaults(table_config);
+        opts.resolve_filter(&HashMap::new()).unwrap();
+        format!("{:?} {:?}", opts.filter, opts.sort)
+    }
+
+    #[test]
+    fn table_defaults_apply_when_no_flags_are_given() {
+        let table_config = table_with_defaults();
+        assert_eq!(options_for(&["rau", "tasks", "-r"], &table_config), r#"Some("{Status} != 'Done'") ["Priority:desc"]"#);
+    }
+
+    #[test]
+    fn filter_flags_override_the_default_filter() {
+        let table_config = table_with_defaults();
+        assert_eq!(options_for(&["rau", "tasks", "-r", "--filter", "{Open}"], &table_config), r#"Some("{Open}") ["Priority:desc"]"#);
+        assert_eq!(options_for(&["rau", "tasks", "-r", "--no-default-filter"], &table_config), r#"None ["Priority:desc"]"#);
+
+        let path = std::env::temp_dir().join(format!("rau-test-filter-{}", std::process::id()));
+        fs::write(&path, "  {FromFile}\n").unwrap();
+        let result = options_for(&["rau", "tasks", "-r", "--filter-file", path.to_str().unwrap()], &table_config);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(result, r#"Some("{FromFile}") ["Priority:desc"]"#);
+    }
+
+    #[test]
+    fn sort_flags_override_the_default_sort() {
+        let table_config = table_with_defaults();
+        assert_eq!(
+            options_for(&["rau", "tasks", "-r", "--sort", "Name", "--sort", "Due:desc"], &table_config),
+            r#"Some("{Status} != 'Done'") ["Name", "Due:desc"]"#
+        );
+    }
+
+    #[test]
+    fn set_never_uses_the_default_filter() {
+        let table_config = table_with_defaults();
+        assert_eq!(options_for(&["rau", "tasks", "set", "Status=Done"], &table_config), r#"None ["Priority:desc"]"#);
+    }
+
+    #[test]
+    fn run_flags_are_rejected_in_table_defaults() {
+        let matches = build_cli().try_get_matches_from(["rau", "tasks", "-r"]).unwrap();
+        let run_flag: HashMap<String, Value> = [("max-retries".to_string(), json!(2))].into();
+        let loading_flag: HashMap<String, Value> = [("no-env".to_string(), json!(true))].into();
+        assert!(with_defaults(&matches, &HashMap::new(), &run_flag).is_err());
+        assert!(with_defaults(&matches, &loading_flag, &HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn defaults_give_way_to_conflicting_flags() {
+        let defaults: HashMap<String, Value> =
+            [("filter".to_string(), json!("{Open}")), ("sample".to_string(), json!(5)), ("seed".to_string(), json!(7))].into();
+        let matches = build_cli().try_get_matches_from(["rau", "tasks", "--filter-file", "open.txt"]).unwrap();
+        assert_eq!(default_args(&matches, &defaults, &HashMap::new()).unwrap(), ["--sample=5", "--seed=7"]);
+
+        let matches = build_cli().try_get_matches_from(["rau", "tasks", "--all", "--parallel-scan"]).unwrap();
+        assert_eq!(default_args(&matches, &defaults, &HashMap::new()).unwrap(), ["--filter={Open}"]);
+
+        let seed_only: HashMap<String, Value> = [("seed".to_string(), json!(7))].into();
+        let matches = build_cli().try_get_matches_from(["rau", "tasks"]).unwrap();
+        assert!(default_args(&matches, &seed_only, &HashMap::new()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn computed_types_include_every_read_only_field() {
+        for field_type in ["formula", "rollup", "multipleLookupValues", "count", "autoNumber", "createdBy", "lastModifiedBy", "button"] {
+            assert!(is_computed_type(field_type), "{}", field_type);
+        }
+        for field_type in ["singleLineText", "number", "multipleRecordLinks", "multipleAttachments"] {
+            assert!(!is_computed_type(field_type), "{}", field_type);
+        }
+    }
+
+    #[test]
+    fn same_field_value_treats_missing_as_empty_and_compares_numbers_by_value() {
+        assert!(same_field_value(&json!(false), None));
+        assert!(same_field_value(&json!(""), None));
+        assert!(same_field_value(&json!([]), None));
+        assert!(!same_field_value(&json!(true), None));
+        assert!(!same_field_value(&json!(0), None));
+        assert!(same_field_value(&json!(3), Some(&json!(3.0))));
+        assert!(!same_field_value(&json!(3), Some(&json!(3.5))));
+        assert!(!same_field_value(&json!("3"), Some(&json!(3))));
+    }
+
+    #[test]
+    fn key_field_or_falls_back_to_the_configured_key_field() {
+        let mut table_config = table_with_defaults();
+        let fields: Vec<Field> = serde_json::from_value(json!([{ "id": "fld1", "name": "Email", "type": "email" }])).unwrap();
+        assert_eq!(key_field_or("Email", &table_config, &fields, "--upsert-on").unwrap(), "Email");
+        assert!(key_field_or("", &table_config, &fields, "--upsert-on").is_err());
+        table_config.key_field = Some("Email".to_string());
+        assert_eq!(key_field_or("", &table_config, &fields, "--upsert-on").unwrap(), "Email");
+        table_config.key_field = Some("Missing".to_string());
+        assert!(key_field_or("", &table_config, &fields, "--upsert-on").is_err());
+    }
+
+    #[tokio::test]
+    async fn dedup_on_skips_imported_rows_whose_key_exists() {
+        let table_config = table_with_defaults();
+        let fields: Vec<Field> = serde_json::from_value(json!([{ "id": "fld1", "name": "Name", "type": "singleLineText" }])).unwrap();
+        let matches = build_cli().try_get_matches_from(["rau", "tasks", "import", "rows.csv", "--dedup-on", "Name"]).unwrap();
+        let opts = Options::from_matches(&matches).unwrap();
+        let policy = ConflictPolicy::from_options(&opts, &table_config, &fields).unwrap().unwrap();
+        assert_eq!((policy.key, policy.mode), ("Name", "skip"));
+
+        let mut client = AirtableClient::new("key");
+        client.quiet_success = true;
+        client.api_url = mock_responses(vec![
+            (200, r#"{"records": [{"id": "recA", "createdTime": "2024-01-01T00:00:00.000Z", "fields": {"Name": "A"}}]}"#),
+            (200, r#"{"records": [{"id": "recB", "createdTime": "2024-01-01T00:00:00.000Z", "fields": {"Name": "B"}}]}"#),
+        ])
+        .await;
+        let rows = [(1, "A"), (2, "B"), (3, "B")].iter().map(|(number, name)| (*number, json!({ "Name": name }).as_object().unwrap().clone())).collect();
+        let skipped = create_records(&client, &table_config, rows, Some(&policy)).await.unwrap();
+        assert_eq!(skipped, 2);
+        assert_eq!(client.requests.load(Ordering::SeqCst), 2);
+        assert_eq!(client.records_affected.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn curl_command_hides_the_api_key_and_configured_headers() {
+        let client = AirtableClient::new("patSECRET");
+        let mut custom = HeaderMap::new();
+        custom.insert("x-api-key", HeaderValue::from_static("gateway-secret"));
+        let request = client.get("https://api.airtable.com/v0/appTEST/Tasks").headers(custom.clone()).header("Accept", "application/json").build().unwrap();
+
+        let command = curl_command(&request, &custom);
+        assert!(!command.contains("patSECRET"));
+        assert!(!command.contains("gateway-secret"));
+        assert!(command.contains(r#"-H 'x-api-key: '"$RAU_HEADER_X_API_KEY""#));
+        assert!(command.contains("-H 'accept: application/json'"));
+    }
+
+    #[test]
+    fn display_helpers_share_truncation() {
+        assert_eq!(truncate_for_display("abcdef", Some(3)), "abc… (6 chars)");
+        assert_eq!(truncate_for_display("abc", Some(3)), "abc");
+        assert_eq!(display_value(&json!("abcdef"), Some(3)), r#""abc… (6 chars)""#);
+        assert_eq!(display_value(&json!("abcdef"), None), r#""abcdef""#);
+        assert_eq!(display_value(&json!(12345), Some(3)), "12345");
+        let record: Record = serde_json::from_value(json!({ "id": "recA", "fields": { "Name": "abc\ndef", "Count": 7 } })).unwrap();
+        assert_eq!(oneline_record(&record, 3), "Count=7; Name=abc… (7 chars)");
+    }
+
+    #[test]
+    fn output_select_rejects_unknown_formats() {
+        assert_eq!(output::select("text", false).unwrap().name(), "text");
+        assert_eq!(output::select("text", true).unwrap().name(), "json");
+        assert!(output::select("yaml", false).is_err());
+    }
+
+    #[test]
+    fn record_diff_serializes_same_only_when_shown() {
+        let mut diff = RecordDiff {
+            id_a: "recA".to_string(),
+            id_b: "recB".to_string(),
+            different: serde_json::from_value(json!({ "Status": { "a": "Todo", "b": "Done" } })).unwrap(),
+            same: None,
+            same_count: 1,
+        };
+        assert_eq!(serde_json::to_value(&diff).unwrap(), json!({ "a": "recA", "b": "recB", "different": { "Status": { "a": "Todo", "b": "Done" } } }));
+        diff.same = serde_json::from_value(json!({ "Name": "Alpha" })).unwrap();
+        assert_eq!(serde_json::to_value(&diff).unwrap()["same"], json!({ "Name": "Alpha" }));
+    }
+
+    #[test]
+    fn merge_arrays_merges_only_multi_value_fields() {
+        let available_fields: Vec<Field> = serde_json::from_value(json!([
+            { "id": "fld1", "name": "Tags", "type": "multipleSelects" },
+            { "id": "fld2", "name": "Name", "type": "singleLineText" },
+        ]))
+        .unwrap();
+        let mut fields: Map<String, Value> = serde_json::from_value(json!({ "Tags": ["b", "c"], "Name": "New" })).unwrap();
+        assert!(merged_array_fields(&available_fields, &fields, false).is_empty());
+        let merged_fields = merged_array_fields(&available_fields, &fields, true);
+        assert_eq!(merged_fields, vec!["Tags".to_string()]);
+
+        let record: Record = serde_json::from_value(json!({ "id": "recA", "fields": { "Tags": ["a", "b"], "Name": "Old" } })).unwrap();
+        merge_into_current(&mut fields, &merged_fields, &record);
+        assert_eq!(Value::Object(fields), json!({ "Tags": ["a", "b", "c"], "Name": "New" }));
+    }
+
+    #[test]
+    fn plan_describes_retries_pacing_and_concurrency() {
+        let table_config = table_with_defaults();
+        let matches = build_cli().try_get_matches_from(["rau", "tasks", "-r", "--all", "--explain"]).unwrap();
+        let plan = plan_text("https://api.example.com", &table_config, &Options::from_matches(&matches).unwrap());
+        assert!(plan.contains("Requests: 2\n"), "{}", plan);
+        assert!(plan.contains("Retries: up to 3 per request"), "{}", plan);
+        assert!(plan.contains("from 500ms to at most 30000ms with full jitter"), "{}", plan);
+        assert!(plan.contains("Pacing: none\n"), "{}", plan);
+        assert!(!plan.contains("Request limit"), "{}", plan);
+        assert!(plan.contains("Concurrency: up to 4 requests at a time"), "{}", plan);
+
+        let matches = build_cli()
+            .try_get_matches_from(["rau", "tasks", "-r", "--explain", "--max-retries", "0", "--base-delay", "250", "--limit-requests", "10", "--max-concurrency", "2"])
+            .unwrap();
+        let plan = plan_text("https://api.example.com", &table_config, &Options::from_matches(&matches).unwrap());
+        assert!(plan.contains("Retries: none"), "{}", plan);
+        assert!(plan.contains("Pacing: 250ms before every request"), "{}", plan);
+        assert!(plan.contains("Request limit: 10"), "{}", plan);
+        assert!(plan.contains("Concurrency: up to 2 requests at a time"), "{}", plan);
+    }
+
+    #[test]
+    fn list_params_encode_sorts_in_order() {
+        let matches = build_cli().try_get_matches_from(["rau", "tasks", "-r", "--all", "--sort", "Name", "--sort", "Due:desc", "--sort", "a:b"]).unwrap();
+        let opts = Options::from_matches(&matches).unwrap();
+        let expected: Vec<(String, String)> = [
+            ("sort[0][field]", "Name"),
+            ("sort[0][direction]", "asc"),
+            ("sort[1][field]", "Due"),
+            ("sort[1][direction]", "desc"),
+            ("sort[2][field]", "a:b"),
+            ("sort[2][direction]", "asc"),
+        ]
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+        assert_eq!(list_params(&opts), expected);
+    }
+
+    #[test]
+    fn retry_delay_doubles_up_to_the_cap() {
+        let policy = RetryPolicy { max_retries: 5, base_ms: 500, max_ms: 2_000, jitter: false };
+        let delays: Vec<u128> = [0, 1, 2, 3, 40].iter().map(|&attempt| policy.delay(attempt, None).as_millis()).collect();
+        assert_eq!(delays, [500, 1_000, 2_000, 2_000, 2_000]);
+
+        let jittered = RetryPolicy { jitter: true, ..policy };
+        assert!((0..50).all(|_| jittered.delay(1, None) <= Duration::from_millis(1_000)));
+    }
+
+    #[test]
+    fn retry_after_wins_when_it_is_longer() {
+        let policy = RetryPolicy { max_retries: 3, base_ms: 500, max_ms: 2_000, jitter: false };
+        assert_eq!(policy.delay(0, Some(Duration::from_secs(5))), Duration::from_secs(5));
+        assert_eq!(policy.delay(2, Some(Duration::from_millis(100))), Duration::from_millis(2_000));
+    }
+
+    // A local server answering each connection with the next status in `statuses`
+    async fn mock_server(statuses: Vec<u16>) -> String {
+        let responses = statuses
+            .into_iter()
+            .map(|status| (status, if status == 200 { r#"{"records": []}"# } else { r#"{"errors": [{"error": "RATE_LIMIT_REACHED"}]}"# }))
+            .collect();
+        mock_responses(responses).await
+    }
+
+    // Answer one request per connection with each status and body in turn
+    async fn mock_responses(responses: Vec<(u16, &'static str)>) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            for (status, body) in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buffer = [0; 1024];
+                while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                    let read = socket.read(&mut buffer).await.unwrap();
+                    if read == 0 {
+                        break;
+                    }
+                    request.extend_from_slice(&buffer[..read]);
+                }
+                let response = format!(
+                    "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        format!("http://{}", address)
+    }
+
+    #[tokio::test]
+    async fn send_retries_rate_limited_requests() {
+        let mut client = AirtableClient::new("key");
+        client.api_url = mock_server(vec![429, 429, 200]).await;
+        client.retry = RetryPolicy { max_retries: 3, base_ms: 1, max_ms: 10, jitter: false };
+
+        let url = format!("{}/v0/appTEST/Tasks", client.api_url);
+        let (status, text) = client.send(client.get(&url)).await.unwrap();
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(client.parse::<RecordsResponse>(&text).unwrap().records.len(), 0);
+        assert_eq!(client.requests.load(Ordering::SeqCst), 3);
+        assert_eq!(client.rate_limited.load(Ordering::SeqCst), 2);
+        assert_eq!(client.retries.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn raw_request_fails_on_error_statuses() {
+        let mut client = AirtableClient::new("key");
+        client.api_url = mock_server(vec![200, 404]).await;
+        let matches = build_cli().try_get_matches_from(["rau", "raw-request", "--path", "/v0/meta/whoami"]).unwrap();
+        assert!(raw_request(&client, &matches).await.is_ok());
+        let err = raw_request(&client, &matches).await.unwrap_err();
+        assert_eq!(exit_code(err.as_ref()), 4);
+    }
+
+    #[tokio::test]
+    async fn send_returns_the_last_429_once_retries_run_out() {
+        let mut client = AirtableClient::new("key");
+        client.api_url = mock_server(vec![429, 429]).await;
+        client.retry = RetryPolicy { max_retries: 1, base_ms: 1, max_ms: 10, jitter: false };
+
+        let url = format!("{}/v0/appTEST/Tasks", client.api_url);
+        let (status, _) = client.send(client.get(&url)).await.unwrap();
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(client.retries.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn copy_to_an_unknown_table_fails() {
+        let mut client = AirtableClient::new("key");
+        client.api_url = mock_responses(vec![(200, r#"{"id": "recA", "fields": {"Name": "Alpha"}}"#), (200, r#"{"tables": []}"#)]).await;
+        let config: Settings = serde_json::from_value(json!({
+            "tables": {
+                "tasks": { "base_id": "appTEST", "table_name": "Tasks" },
+                "archive": { "base_id": "appTEST", "table_name": "Archive" },
+            },
+            "paths": { "config_file": "config.toml", "cache_file": "cache.json" },
+        }))
+        .unwrap();
+
+        let fields = vec!["Name".to_string()];
+        let err = copy_record(&client, &config, &config.tables["tasks"], &fields, "recA", Some(&"archive".to_string())).await.unwrap_err();
+        assert_eq!(exit_code(err.as_ref()), 4);
+        assert_eq!(client.requests.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn export_sqlite_rejects_an_id_column_and_recreates_a_drifted_table() {
+        let path = std::env::temp_dir().join(format!("rau-export-{}.db", std::process::id()));
+        let path = path.to_str().unwrap();
+        let table_config = table_with_defaults();
+        let client = AirtableClient::new("key");
+
+        let clashing: Vec<Field> = serde_json::from_value(json!([{ "id": "fld1", "name": "ID", "type": "singleLineText" }])).unwrap();
+        let err = export_sqlite(&client, &table_config, &clashing, &HashMap::new(), path).await.unwrap_err();
+        assert_eq!(exit_code(err.as_ref()), 2);
+
+        let conn = rusqlite::Connection::open(path).unwrap();
+        conn.execute("CREATE TABLE \"Tasks\" (\"id\" TEXT PRIMARY KEY, \"Old\" TEXT)", []).unwrap();
+        conn.execute("INSERT INTO \"Tasks\" VALUES ('recOLD', 'x')", []).unwrap();
+
+        let mut client = AirtableClient::new("key");
+        client.api_url = mock_responses(vec![(200, r#"{"records": [{"id": "recA", "fields": {"Name": "Alpha"}}]}"#)]).await;
+        let fields: Vec<Field> = serde_json::from_value(json!([{ "id": "fld1", "name": "Name", "type": "singleLineText" }])).unwrap();
+        export_sqlite(&client, &table_config, &fields, &HashMap::new(), path).await.unwrap();
+
+        let rows: Vec<(String, String)> = conn
+            .prepare("SELECT \"id\", \"Name\" FROM \"Tasks\"")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(rows, vec![("recA".to_string(), "Alpha".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn startup_retries_replace_per_request_connection_retries() {
+        // A port nothing listens on, so every attempt is refused
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        drop(listener);
+
+        let mut client = AirtableClient::new("key");
+        client.api_url = format!("http://{}", address);
+        client.retry = RetryPolicy { max_retries: 3, base_ms: 1, max_ms: 10, jitter: false };
+        let cache_file = std::env::temp_dir().join(format!("rau-cache-{}.json", std::process::id()));
+
+        let err = refresh_schema_cache(&client, &table_with_defaults(), cache_file.to_str().unwrap(), 2).await.unwrap_err();
+        assert!(is_connection_error(err.as_ref()));
+        assert_eq!(client.requests.load(Ordering::SeqCst), 3);
+    }
+
+    #[cfg(feature = "blocking")]
+    #[tokio::test]
+    async fn blocking_get_retries_server_errors_and_maps_failures() {
+        let url = format!("{}/v0/appTEST/Tasks/rec1", mock_responses(vec![(503, "{}"), (200, r#"{"id": "rec1", "fields": {}}"#), (404, "{}")]).await);
+        let retry = RetryPolicy { max_retries: 1, base_ms: 1, max_ms: 10, jitter: false };
+        let (first, second) = tokio::task::spawn_blocking(move || {
+            let client = reqwest::blocking::Client::new();
+            let first = blocking::get(&client, "key", &url, &retry, "Failed to query record").map_err(|err| err.to_string());
+            let second = blocking::get(&client, "key", &url, &retry, "Failed to query record").map_err(|err| exit_code(err.as_ref()));
+            (first, second)
+        })
+        .await
+        .unwrap();
+        assert_eq!(parse_record(&first.unwrap()).unwrap().id, "rec1");
+        assert_eq!(second.unwrap_err(), 4);
+    }
+}
@@ -0,0 +1,261 @@
+// Output formats for queried records and listings, selected with `--format`. Commands hand their
+// records to the selected format instead of deciding how they look; a new format implements
+// `OutputFormat` and is added to `FORMATS`. Whether stdout is a terminal is decided here too.
+
+use crate::{display_value, field_path_value, print_url, truncate_for_display, Options, RauError, Record, RecordDiff, View};
+use serde::{Serialize, Serializer};
+use serde_json::Value;
+use std::io::{self, IsTerminal};
+
+pub trait OutputFormat: Sync {
+    // Name given to --format
+    fn name(&self) -> &'static str;
+
+    // Print a queried record: every field, or only the named ones, and its web URL if given
+    fn render_record(&self, record: &Record, fields: &[&str], url: Option<&str>, opts: &Options) -> serde_json::Result<()>;
+
+    // Print a listing of records
+    fn render_records(&self, records: &[Record], opts: &Options) -> serde_json::Result<()>;
+
+    // Print records fetched by ID, each with every field
+    fn render_full_records(&self, records: &[Record], opts: &Options) -> serde_json::Result<()>;
+
+    // Print a listing grouped by a field's value, as (value, records) pairs in order
+    fn render_groups(&self, groups: &[(String, Vec<&Record>)], opts: &Options) -> serde_json::Result<()>;
+
+    // Print how two records differ
+    fn render_diff(&self, diff: &RecordDiff, opts: &Options) -> serde_json::Result<()>;
+
+    // Print a table's views
+    fn render_views(&self, views: &[View], opts: &Options) -> serde_json::Result<()>;
+
+    // Print the choices of a select field, as Airtable describes them
+    fn render_choices(&self, choices: &[Value], opts: &Options) -> serde_json::Result<()>;
+}
+
+// Every format --format accepts besides `auto`
+static FORMATS: &[&dyn OutputFormat] = &[&Text, &Json];
+
+// Names accepted by --format
+pub fn names() -> Vec<&'static str> {
+    std::iter::once("auto").chain(FORMATS.iter().map(|format| format.name())).collect()
+}
+
+// The format for --format and --json: `auto` prints text on a terminal and JSON when piped
+pub fn select(name: &str, json: bool) -> Result<&'static dyn OutputFormat, RauError> {
+    let name = match name {
+        _ if json => "json",
+        "auto" if stdout_is_terminal() => "text",
+        "auto" => "json",
+        name => name,
+    };
+    FORMATS
+        .iter()
+        .copied()
+        .find(|format| format.name() == name)
+        .ok_or_else(|| RauError::Usage(format!("Unknown output format '{}', expected one of: {}", name, names().join(", "))))
+}
+
+// Whether stdout is a terminal, where `auto` picks text and JSON is pretty-printed
+pub fn stdout_is_terminal() -> bool {
+    io::stdout().is_terminal()
+}
+
+// Pretty-print JSON for a terminal and keep it compact for pipes
+fn print_json<T: Serialize + ?Sized>(value: &T, opts: &Options) -> serde_json::Result<()> {
+    if opts.pretty {
+        println!("{}", serde_json::to_string_pretty(value)?);
+    } else {
+        println!("{}", serde_json::to_string(value)?);
+    }
+    Ok(())
+}
+
+// A listed record as `ID: <id>, Name: <name>`
+fn print_record_line(record: &Record, opts: &Options) {
+    let name = record.fields.get("Name").and_then(|v| v.as_str()).unwrap_or("<no name>");
+    let name = truncate_for_display(name, opts.max_field_length);
+    print!("ID{}{}, Name{}{}{}", opts.field_separator, record.id, opts.field_separator, name, opts.record_separator);
+}
+
+// `field: value` lines for a record and one line per listed record
+struct Text;
+
+impl OutputFormat for Text {
+    fn name(&self) -> &'static str {
+        "text"
+    }
+
+    fn render_record(&self, record: &Record, fields: &[&str], url: Option<&str>, opts: &Options) -> serde_json::Result<()> {
+        if fields.is_empty() {
+            for (field, value) in record.fields.as_object().into_iter().flatten() {
+                println!("{}{}{}", field, opts.field_separator, display_value(value, opts.max_field_length));
+            }
+        }
+        for field in fields {
+            if let Some(value) = field_path_value(&record.fields, field).as_ref() {
+                println!("{}{}{}", field, opts.field_separator, display_value(value, opts.max_field_length));
+            } else {
+                println!("{}{}<no value>", field, opts.field_separator);
+            }
+        }
+        if let Some(url) = url {
+            print_url(url);
+        }
+        Ok(())
+    }
+
+    fn render_records(&self, records: &[Record], opts: &Options) -> serde_json::Result<()> {
+        for record in records {
+            print_record_line(record, opts);
+        }
+        Ok(())
+    }
+
+    fn render_full_records(&self, records: &[Record], opts: &Options) -> serde_json::Result<()> {
+        for record in records {
+            println!("ID{}{}", opts.field_separator, record.id);
+            self.render_record(record, &[], None, opts)?;
+        }
+        Ok(())
+    }
+
+    fn render_groups(&self, groups: &[(String, Vec<&Record>)], opts: &Options) -> serde_json::Result<()> {
+        for (key, members) in groups {
+            println!("== {} ({}) ==", key, members.len());
+            for record in members {
+                print_record_line(record, opts);
+            }
+        }
+        Ok(())
+    }
+
+    fn render_diff(&self, diff: &RecordDiff, opts: &Options) -> serde_json::Result<()> {
+        let show = |value: &Value| if value.is_null() { "<no value>".to_string() } else { display_value(value, opts.max_field_length) };
+        eprintln!("--- {}", diff.id_a);
+        eprintln!("+++ {}", diff.id_b);
+        for (name, values) in &diff.different {
+            println!("- {}{}{}", name, opts.field_separator, show(&values["a"]));
+            println!("+ {}{}{}", name, opts.field_separator, show(&values["b"]));
+        }
+        for (name, value) in diff.same.iter().flatten() {
+            println!("  {}{}{}", name, opts.field_separator, show(value));
+        }
+        eprintln!("{} field(s) differ, {} the same", diff.different.len(), diff.same_count);
+        Ok(())
+    }
+
+    fn render_views(&self, views: &[View], opts: &Options) -> serde_json::Result<()> {
+        for view in views {
+            println!("{}{}{} ({})", view.name, opts.field_separator, view.id, view.view_type);
+        }
+        Ok(())
+    }
+
+    fn render_choices(&self, choices: &[Value], _opts: &Options) -> serde_json::Result<()> {
+        for choice in choices {
+            println!("{}", choice.get("name").and_then(Value::as_str).unwrap_or_default());
+        }
+        Ok(())
+    }
+}
+
+// The record as Airtable returned it, or an object of the queried fields, and listings as an array
+struct Json;
+
+impl OutputFormat for Json {
+    fn name(&self) -> &'static str {
+        "json"
+    }
+
+    // The web URL goes in a `url` key, unless a queried field already has that name
+    fn render_record(&self, record: &Record, fields: &[&str], url: Option<&str>, opts: &Options) -> serde_json::Result<()> {
+        if fields.is_empty() {
+            return print_json(&WithUrl { record, url }, opts);
+        }
+        let mut values = Vec::new();
+        for field in fields {
+            if !values.iter().any(|(name, _)| name == field) {
+                values.push((*field, field_path_value(&record.fields, field).unwrap_or(Value::Null)));
+            }
+        }
+        let url = url.filter(|_| !fields.contains(&"url"));
+        print_json(&WithUrl { record: &OrderedFields(values), url }, opts)
+    }
+
+    fn render_records(&self, records: &[Record], opts: &Options) -> serde_json::Result<()> {
+        print_json(records, opts)
+    }
+
+    fn render_full_records(&self, records: &[Record], opts: &Options) -> serde_json::Result<()> {
+        print_json(records, opts)
+    }
+
+    // An array of groups in listing order, each with its value, count and records
+    fn render_groups(&self, groups: &[(String, Vec<&Record>)], opts: &Options) -> serde_json::Result<()> {
+        let groups: Vec<Group> = groups.iter().map(|(value, records)| Group { value, count: records.len(), records }).collect();
+        print_json(&groups, opts)
+    }
+
+    fn render_diff(&self, diff: &RecordDiff, opts: &Options) -> serde_json::Result<()> {
+        print_json(diff, opts)
+    }
+
+    fn render_views(&self, views: &[View], opts: &Options) -> serde_json::Result<()> {
+        print_json(views, opts)
+    }
+
+    fn render_choices(&self, choices: &[Value], opts: &Options) -> serde_json::Result<()> {
+        print_json(choices, opts)
+    }
+}
+
+// Queried fields as an object in the order they were asked for, which a serde_json Map would sort
+struct OrderedFields<'a>(Vec<(&'a str, Value)>);
+
+impl Serialize for OrderedFields<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.0.iter().map(|(name, value)| (name, value)))
+    }
+}
+
+// A record object with the record's web URL added after its other keys
+#[derive(Serialize)]
+struct WithUrl<'a, T: Serialize> {
+    #[serde(flatten)]
+    record: &'a T,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<&'a str>,
+}
+
+// One --group-by group in JSON output
+#[derive(Serialize)]
+struct Group<'a> {
+    value: &'a str,
+    count: usize,
+    records: &'a [&'a Record],
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn queried_fields_keep_their_order() {
+        let fields = OrderedFields(vec![("Zeta", json!(1)), ("Alpha", json!(2))]);
+        let json = serde_json::to_string(&WithUrl { record: &fields, url: Some("https://airtable.com/app/tbl/rec") }).unwrap();
+        assert_eq!(json, r#"{"Zeta":1,"Alpha":2,"url":"https://airtable.com/app/tbl/rec"}"#);
+    }
+
+    #[test]
+    fn groups_are_an_array_in_listing_order() {
+        let record: Record = serde_json::from_value(json!({ "id": "rec1", "fields": {} })).unwrap();
+        let records = [&record];
+        let groups = [Group { value: "Open", count: 1, records: &records }, Group { value: "Done", count: 0, records: &[] }];
+        let json = serde_json::to_value(groups).unwrap();
+        assert_eq!(json[0]["value"], "Open");
+        assert_eq!(json[0]["records"][0]["id"], "rec1");
+        assert_eq!(json[1]["value"], "Done");
+    }
+}